@@ -1,17 +1,25 @@
-import json
-import os
+import inspect
 import uuid
 from functools import wraps
 from typing import Callable, Union, Any
 
-from .pywui_rs import WindowManager
+from .pywui_rs import ConfigError, WindowManager, config_schema, load_config, pack_assets
 from .window import Window
 
 __all__ = [
-    "WindowManager"
+    "WindowManager",
+    "CommandNotFoundError",
+    "ConfigError",
+    "config_schema",
+    "load_config",
+    "pack_assets",
 ]
 
 
+class CommandNotFoundError(LookupError):
+    pass
+
+
 class PyWui:
     _commands: dict[str, Callable] = {}
     _listeners: dict[str, list[Callable]] = {}
@@ -19,11 +27,12 @@ class PyWui:
     _on_stop_listener: list[Callable] = []
     _windows: dict[str, Window] = {}
 
-    def __init__(self, config_path: Union[str, None] = None):
-        full_path = os.path.join(config_path or os.getcwd(), 'pywui.conf.json')
-        if not os.path.exists(full_path):
-            raise Exception(f"{full_path} not exist")
-
+    def __init__(
+            self,
+            config_path: Union[str, None] = None,
+            mode: Union[str, None] = None,
+            strict: bool = False,
+    ):
         def handle_request(info: dict):
             return self._handler_request(info)
 
@@ -36,26 +45,20 @@ class PyWui:
         def handle_stop(info: dict = None):
             return self._on_stop(info)
 
-        config_dict = self._parse_config(self._load_config(full_path))
+        # A config file or its directory; otherwise searched for upward from the working directory.
+        config_dict = self._parse_config(load_config(config_path, strict=strict))
 
-        assets_dir = config_dict.get("build", {}).get("buildPath")
         self._manager = WindowManager(
             command=handle_request,
             listener=handle_event,
             on_start=handle_start,
             on_stop=handle_stop,
             config=config_dict,
-            assets_dir=assets_dir
+            mode=mode,
         )
         print(self._manager)
         self._create_windows(config_dict)
 
-    @classmethod
-    def _load_config(cls, path: str) -> dict:
-        with open(path, 'r') as f:
-            text = f.read()
-            return json.loads(text)
-
     @classmethod
     def _parse_config(cls, config: dict) -> dict:
         pywui: Union[dict, None] = config.get("pywui", {})
@@ -87,6 +90,11 @@ class PyWui:
                 label: str = win.get("label")
                 self._windows[label] = Window(label, self._manager)
 
+    @property
+    def mode(self) -> str:
+        """`"dev"` or `"prod"`, from the constructor, `PYWUI_MODE` or `build.mode`."""
+        return self._manager.mode
+
     def get_window(self, label: str = "main") -> Union[Window, None]:
         return self._windows.get(label)
 
@@ -98,13 +106,6 @@ class PyWui:
         elements.append(callback)
         self._listeners[name] = elements
 
-    @classmethod
-    def _create_response(cls, data: Any, error: Any = None) -> dict:
-        return {
-            "error": error,
-            "data": data
-        }
-
     def _on_start(self, info: dict = None):
         for callback in self._on_start_listener:
             try:
@@ -120,29 +121,37 @@ class PyWui:
                 print("Error", e)
 
     def _handler_request(self, info: dict):
+        # Exceptions propagate to the Rust side, which reports them to the invoking webview.
         args = info['args']
         command = info["command"]
-        try:
-            if command in self._commands:
-                command_handler = self._commands.get(command)
-                result = command_handler(*args)
-                return self._create_response(result)
-            else:
-                return self._create_response(None, "Command found")
-        except Exception as e:
-            print("Error : ", str(e))
-            return self._create_response(None, str(e))
+        if command not in self._commands:
+            raise CommandNotFoundError(f"Command '{command}' not found")
+        command_handler = self._commands.get(command)
+        return command_handler(*args)
 
     def _handler_event(self, info: dict):
         args = info['args']
         event = info["event"]
+        pending = []
         if event in self._listeners:
             listeners = self._listeners.get(event)
             for listener in listeners:
                 try:
-                    listener(*args)
+                    result = listener(*args)
+                    if inspect.isawaitable(result):
+                        pending.append(result)
                 except Exception as e:
                     print("Error", e)
+        if pending:
+            return self._await_listeners(pending)
+
+    @classmethod
+    async def _await_listeners(cls, pending: list):
+        for awaitable in pending:
+            try:
+                await awaitable
+            except Exception as e:
+                print("Error", e)
 
     def command(self, name: str):
         def decorator(callback: Callable):
@@ -168,6 +177,25 @@ class PyWui:
 
         return decorator
 
+    def protocol(self, scheme: Union[str, None] = None, prefix: Union[str, None] = None):
+        """Serve a custom scheme, or the paths below `prefix` of the asset protocol, from a callback.
+
+        The callback receives a dict with `method`, `uri`, `path`, `query`, `headers` and `body`
+        and returns `(status, headers, body)`, a dict with those keys, or just the body.
+        Coroutine functions are awaited.
+        """
+        def decorator(callback: Callable):
+            self._manager.register_protocol(callback, scheme, prefix)
+            return callback
+
+        return decorator
+
+    def emit(self, event: str, data: Any = None, exclude: Union[str, list[str], None] = None):
+        self._manager.emit(event, data, exclude)
+
+    def emit_to(self, target: Union[str, list[str]], event: str, data: Any = None):
+        self._manager.emit_to(target, event, data)
+
     def on_start(self, callback: Callable):
         self._on_start_listener.append(callback)
         return callback
@@ -1,5 +1,16 @@
-from typing import Callable, Union
+from typing import Any, Callable, Union
 
+PROTOCOL_VERSION: int
+
+class ConfigError(ValueError):
+    path: str
+    file: Union[str, None]
+
+def load_config(path: Union[str, None] = None, strict: bool = False) -> dict[str, Any]: ...
+
+def config_schema() -> dict[str, Any]: ...
+
+def pack_assets(src: str, out: str) -> int: ...
 
 class WindowManager:
 
@@ -10,16 +21,34 @@ class WindowManager:
             on_start: Callable,
             on_stop: Callable,
             config: dict,
-            assets_dir: str,
+            assets_dir: Union[str, None] = None,
+            mode: Union[str, None] = None,
     ): ...
 
-    def emit(self, event: str, data: any): ...
+    @property
+    def mode(self) -> str: ...
+
+    def emit(self, event: str, data: any, exclude: Union[str, list[str], None] = None): ...
+
+    def emit_to(self, target: Union[str, list[str]], event: str, data: any): ...
 
     def close_window(self, label: Union[str, None] = None): ...
 
+    def register_protocol(
+            self,
+            handler: Callable[[dict], Any],
+            scheme: Union[str, None] = None,
+            prefix: Union[str, None] = None,
+    ): ...
+
     def update_window(self, label: str, updates: dict): ...
 
-    def update_webview(self, label: str, updates: dict): ...
+    def update_webview(
+            self,
+            label: str,
+            updates: dict,
+            callback: Union[Callable[[dict], None], None] = None,
+    ): ...
 
     def run(self): ...
     def test_called_from_python(self): ...
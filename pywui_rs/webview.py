@@ -1,3 +1,5 @@
+from typing import Callable, Union
+
 from .pywui_rs import WindowManager
 
 
@@ -9,10 +11,12 @@ class Webview:
             "devtools": devtools
         })
 
-    def eval(self, script: str):
+    def eval(self, script: str, callback: Union[Callable[[dict], None], None] = None):
+        """Run `script` in the page. With a callback the script is a function body: whatever it
+        `return`s is passed back as `{"data": ...}`, a thrown error as `{"error": ...}`."""
         self._manager.update_webview(self._label, {
             "script": script
-        })
+        }, callback)
 
     def load_url(self, url: str):
         self._manager.update_webview(self._label, {
@@ -12,6 +12,9 @@ class Window:
     def get_webview(self) -> Webview:
         return self._webview
 
+    def update(self, **updates):
+        self._manager.update_window(self._label, updates)
+
     def resize(self, width: int, height: int):
         self._manager.update_window(
             self._label, {
@@ -61,6 +64,9 @@ class Window:
                 "maximizable": value,
             })
 
+    def emit(self, event: str, data=None):
+        self._manager.emit_to(self._label, event, data)
+
     def close(self):
         self._manager.close_window(self._label)
 
@@ -0,0 +1,73 @@
+use std::sync::Mutex;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyCFunction, PyDict, PyTuple};
+
+const HELPERS: &std::ffi::CStr = c"
+async def resolve(awaitable):
+    return await awaitable
+";
+
+/// Drives awaitables returned by Python callbacks on a dedicated asyncio loop thread.
+pub struct AsyncioRunner {
+    event_loop: PyObject,
+    resolve: PyObject,
+    is_awaitable: PyObject,
+    run_coroutine_threadsafe: PyObject,
+}
+
+impl AsyncioRunner {
+    pub fn start(py: Python) -> PyResult<Self> {
+        let asyncio = py.import("asyncio")?;
+        let event_loop = asyncio.call_method0("new_event_loop")?;
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("target", event_loop.getattr("run_forever")?)?;
+        kwargs.set_item("name", "pywui-asyncio")?;
+        kwargs.set_item("daemon", true)?;
+        py.import("threading")?
+            .getattr("Thread")?
+            .call((), Some(&kwargs))?
+            .call_method0("start")?;
+        let helpers = PyModule::from_code(py, HELPERS, c"pywui_asyncio.py", c"pywui_asyncio")?;
+        Ok(Self {
+            event_loop: event_loop.unbind(),
+            resolve: helpers.getattr("resolve")?.unbind(),
+            is_awaitable: py.import("inspect")?.getattr("isawaitable")?.unbind(),
+            run_coroutine_threadsafe: asyncio.getattr("run_coroutine_threadsafe")?.unbind(),
+        })
+    }
+
+    pub fn is_awaitable(&self, py: Python, value: &PyObject) -> bool {
+        self.is_awaitable
+            .call1(py, (value,))
+            .and_then(|result| result.extract::<bool>(py))
+            .unwrap_or(false)
+    }
+
+    /// Schedules `awaitable` on the loop and calls `on_done` with its outcome, on the loop thread.
+    pub fn submit<F>(&self, py: Python, awaitable: PyObject, on_done: F) -> PyResult<()>
+    where
+        F: FnOnce(Python, PyResult<PyObject>) + Send + 'static,
+    {
+        let coroutine = self.resolve.call1(py, (awaitable,))?;
+        let future = self
+            .run_coroutine_threadsafe
+            .call1(py, (coroutine, self.event_loop.clone_ref(py)))?;
+        let on_done = Mutex::new(Some(on_done));
+        let callback = PyCFunction::new_closure(
+            py,
+            None,
+            None,
+            move |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+                let future = args.get_item(0)?;
+                let result = future.call_method0("result").map(Bound::unbind);
+                if let Some(on_done) = on_done.lock().unwrap().take() {
+                    on_done(args.py(), result);
+                }
+                Ok(())
+            },
+        )?;
+        future.call_method1(py, "add_done_callback", (callback,))?;
+        Ok(())
+    }
+}
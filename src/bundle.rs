@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Leading bytes of every asset bundle, followed by a little-endian `u64` index length,
+/// the JSON index and the concatenated file contents.
+const MAGIC: &[u8; 8] = b"PYWUIPK1";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct BundleEntry {
+    offset: u64,
+    len: u64,
+    /// Milliseconds since the Unix epoch.
+    modified: u64,
+}
+
+/// A single-file archive of a frontend build, held in memory and served by the protocol handler.
+#[derive(Debug)]
+pub struct Bundle {
+    index: BTreeMap<String, BundleEntry>,
+    data: Vec<u8>,
+}
+
+impl Bundle {
+    /// Whether `path` is a file starting with the bundle magic.
+    pub fn is_bundle(path: &Path) -> bool {
+        let mut magic = [0; 8];
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok_and(|_| &magic == MAGIC)
+    }
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    fn from_bytes(mut bytes: Vec<u8>) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        if bytes.len() < 16 || &bytes[..8] != MAGIC {
+            return Err(invalid("not a pywui asset bundle"));
+        }
+        let index_len = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        let index_end = 16usize
+            .checked_add(index_len)
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| invalid("truncated asset bundle index"))?;
+        let index: BTreeMap<String, BundleEntry> =
+            serde_json::from_slice(&bytes[16..index_end]).map_err(|_| invalid("corrupt asset bundle index"))?;
+        let data = bytes.split_off(index_end);
+        let fits = |entry: &BundleEntry| entry.offset.checked_add(entry.len).is_some_and(|end| end <= data.len() as u64);
+        if !index.values().all(fits) {
+            return Err(invalid("truncated asset bundle"));
+        }
+        Ok(Self { index, data })
+    }
+
+    /// Contents and modification time of the file stored under `path` (`/`-separated, no leading slash).
+    pub fn get(&self, path: &str) -> Option<(&[u8], SystemTime)> {
+        let entry = self.index.get(path)?;
+        let start = entry.offset as usize;
+        let modified = UNIX_EPOCH + Duration::from_millis(entry.modified);
+        Some((&self.data[start..start + entry.len as usize], modified))
+    }
+}
+
+/// Packs every file below `src` into a bundle at `out`, returning how many files were stored.
+///
+/// The bundle is written next to `out` and renamed over it, so a failed pack leaves an existing
+/// bundle untouched.
+pub fn pack_dir(src: &Path, out: &Path) -> io::Result<usize> {
+    let file_name = out
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file path", out.display())))?;
+    let partial = out.with_file_name(format!(".{}.partial", file_name.to_string_lossy()));
+    let mut files = vec![];
+    collect_files(&src.canonicalize()?, src, "", &mut files)?;
+    // Packing into the source directory must not include a previous bundle.
+    let skipped: Vec<PathBuf> = [out, &partial].iter().filter_map(|path| path.canonicalize().ok()).collect();
+    files.retain(|(_, path)| path.canonicalize().map_or(true, |path| !skipped.contains(&path)));
+
+    let mut index = BTreeMap::new();
+    let mut offset = 0;
+    for (name, path) in &files {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or_default();
+        index.insert(name.clone(), BundleEntry { offset, len: metadata.len(), modified });
+        offset += metadata.len();
+    }
+
+    match write_bundle(&partial, &index, &files) {
+        Ok(()) => fs::rename(&partial, out)?,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    }
+    Ok(files.len())
+}
+
+fn write_bundle(out: &Path, index: &BTreeMap<String, BundleEntry>, files: &[(String, PathBuf)]) -> io::Result<()> {
+    let index_json = serde_json::to_vec(index)?;
+    let mut writer = BufWriter::new(File::create(out)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(index_json.len() as u64).to_le_bytes())?;
+    writer.write_all(&index_json)?;
+    for (name, path) in files {
+        let mut file = File::open(path)?;
+        let copied = io::copy(&mut file, &mut writer)?;
+        if copied != index[name].len {
+            return Err(io::Error::other(format!("{} changed while packing", path.display())));
+        }
+    }
+    writer.flush()
+}
+
+/// Collects the files below `dir`, skipping any that resolve outside the canonical `root`.
+fn collect_files(root: &Path, dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            eprintln!("Skipping non UTF-8 asset name: {}", entry.path().display());
+            continue;
+        };
+        let name = format!("{}{}", prefix, name);
+        let path = entry.path();
+        // Following linked directories could loop forever or pack files from outside the root.
+        if fs::symlink_metadata(&path)?.is_symlink() && path.is_dir() {
+            eprintln!("Skipping symlinked asset directory: {}", path.display());
+        } else if path.is_dir() {
+            collect_files(root, &path, &format!("{}/", name), files)?;
+        } else if path.is_file() {
+            if path.canonicalize()?.starts_with(root) {
+                files.push((name, path));
+            } else {
+                eprintln!("Skipping asset linked from outside {}: {}", root.display(), path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_and_reads_back_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("dist");
+        fs::create_dir_all(src.join("assets")).unwrap();
+        fs::write(src.join("index.html"), "<html></html>").unwrap();
+        fs::write(src.join("assets/app.js"), "console.log(1)").unwrap();
+        let out = dir.path().join("app.pywui");
+
+        assert_eq!(pack_dir(&src, &out).unwrap(), 2);
+        assert!(Bundle::is_bundle(&out));
+        assert!(!Bundle::is_bundle(&src.join("index.html")));
+
+        let bundle = Bundle::open(&out).unwrap();
+        assert_eq!(bundle.get("index.html").unwrap().0, b"<html></html>");
+        assert_eq!(bundle.get("assets/app.js").unwrap().0, b"console.log(1)");
+        assert!(bundle.get("assets").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_symlinked_directories() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("dist");
+        fs::create_dir_all(src.join("assets")).unwrap();
+        fs::create_dir(dir.path().join("outside")).unwrap();
+        fs::write(src.join("assets/app.js"), "console.log(1)").unwrap();
+        fs::write(dir.path().join("outside/secret.txt"), "secret").unwrap();
+        symlink(&src, src.join("assets/loop")).unwrap();
+        symlink(dir.path().join("outside"), src.join("outside")).unwrap();
+        symlink(dir.path().join("outside/secret.txt"), src.join("secret.txt")).unwrap();
+        symlink(src.join("assets/app.js"), src.join("main.js")).unwrap();
+        let out = dir.path().join("app.pywui");
+
+        assert_eq!(pack_dir(&src, &out).unwrap(), 2);
+        let bundle = Bundle::open(&out).unwrap();
+        assert!(bundle.get("assets/app.js").is_some());
+        assert_eq!(bundle.get("main.js").unwrap().0, b"console.log(1)");
+        assert!(bundle.get("outside/secret.txt").is_none());
+        assert!(bundle.get("secret.txt").is_none());
+    }
+
+    #[test]
+    fn keeps_the_previous_bundle_when_packing_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("dist");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("index.html"), "<html></html>").unwrap();
+        let out = src.join("app.pywui");
+        assert_eq!(pack_dir(&src, &out).unwrap(), 1);
+        assert_eq!(pack_dir(&src, &out).unwrap(), 1);
+        let packed = fs::read(&out).unwrap();
+
+        assert!(pack_dir(&dir.path().join("missing"), &out).is_err());
+        assert_eq!(fs::read(&out).unwrap(), packed);
+        assert_eq!(fs::read_dir(&src).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn rejects_truncated_bundles() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("dist");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("index.html"), "<html></html>").unwrap();
+        let out = dir.path().join("app.pywui");
+        pack_dir(&src, &out).unwrap();
+
+        let mut bytes = fs::read(&out).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(Bundle::from_bytes(bytes).is_err());
+        assert!(Bundle::from_bytes(b"PYWUIPK1".to_vec()).is_err());
+    }
+}
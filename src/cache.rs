@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use http::header::{IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use http::HeaderMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// `Cache-Control` value for request paths matching `pattern` (`*` and `?` wildcards).
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+pub struct CacheControlRule {
+    pub pattern: String,
+    pub value: String,
+}
+
+/// Limits of the in-memory asset cache.
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+pub struct MemoryCacheConfig {
+    #[serde(rename = "maxBytes")]
+    pub max_bytes: Option<u64>,
+    /// Files larger than this are always read from disk.
+    #[serde(rename = "maxFileSize")]
+    pub max_file_size: Option<u64>,
+}
+
+struct Entry {
+    etag: String,
+    data: Arc<Vec<u8>>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<PathBuf, Entry>,
+    bytes: u64,
+    clock: u64,
+}
+
+/// Least-recently-used cache of file contents, keyed by path and validated by entity tag
+/// so a file changed on disk is never served stale.
+pub struct AssetCache {
+    max_bytes: u64,
+    max_file_size: u64,
+    state: Mutex<State>,
+}
+
+impl std::fmt::Debug for AssetCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssetCache")
+            .field("max_bytes", &self.max_bytes)
+            .field("max_file_size", &self.max_file_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AssetCache {
+    pub fn new(config: &MemoryCacheConfig) -> Self {
+        Self {
+            max_bytes: config.max_bytes.unwrap_or(32 * 1024 * 1024),
+            max_file_size: config.max_file_size.unwrap_or(2 * 1024 * 1024),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn accepts(&self, len: u64) -> bool {
+        len <= self.max_file_size && len <= self.max_bytes
+    }
+
+    pub fn get(&self, path: &Path, etag: &str) -> Option<Arc<Vec<u8>>> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let entry = state.entries.get_mut(path)?;
+        if entry.etag != etag {
+            return None;
+        }
+        entry.last_used = clock;
+        Some(entry.data.clone())
+    }
+
+    pub fn insert(&self, path: &Path, etag: &str, data: Arc<Vec<u8>>) {
+        let len = data.len() as u64;
+        if !self.accepts(len) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let entry = Entry {
+            etag: etag.to_string(),
+            data,
+            last_used: state.clock,
+        };
+        if let Some(previous) = state.entries.insert(path.to_path_buf(), entry) {
+            state.bytes -= previous.data.len() as u64;
+        }
+        state.bytes += len;
+        while state.bytes > self.max_bytes {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = state.entries.remove(&oldest) {
+                state.bytes -= evicted.data.len() as u64;
+            }
+        }
+    }
+}
+
+/// Whether the request's conditional headers show the client already holds this version.
+///
+/// `If-None-Match` wins over `If-Modified-Since` when both are present.
+pub fn is_not_modified(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some(if_none_match) = header(IF_NONE_MATCH) {
+        let opaque = etag.trim_start_matches("W/");
+        return if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == opaque);
+    }
+    match (header(IF_MODIFIED_SINCE).map(httpdate::parse_http_date), modified) {
+        (Some(Ok(since)), Some(modified)) => {
+            // HTTP dates have whole-second precision.
+            httpdate::fmt_http_date(modified) == httpdate::fmt_http_date(since) || modified <= since
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn headers(name: http::header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn matches_if_none_match() {
+        assert!(is_not_modified(&headers(IF_NONE_MATCH, "\"a\", \"b\""), "\"b\"", None));
+        assert!(is_not_modified(&headers(IF_NONE_MATCH, "W/\"b\""), "\"b\"", None));
+        assert!(is_not_modified(&headers(IF_NONE_MATCH, "*"), "\"b\"", None));
+        assert!(!is_not_modified(&headers(IF_NONE_MATCH, "\"a\""), "\"b\"", None));
+    }
+
+    #[test]
+    fn matches_if_modified_since() {
+        let modified = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        let same_second = httpdate::fmt_http_date(modified);
+        let earlier = httpdate::fmt_http_date(modified - Duration::from_secs(60));
+        assert!(is_not_modified(&headers(IF_MODIFIED_SINCE, &same_second), "\"b\"", Some(modified)));
+        assert!(!is_not_modified(&headers(IF_MODIFIED_SINCE, &earlier), "\"b\"", Some(modified)));
+        assert!(!is_not_modified(&HeaderMap::new(), "\"b\"", Some(modified)));
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries() {
+        let cache = AssetCache::new(&MemoryCacheConfig {
+            max_bytes: Some(10),
+            max_file_size: None,
+        });
+        cache.insert(Path::new("a"), "1", Arc::new(vec![0; 4]));
+        cache.insert(Path::new("b"), "1", Arc::new(vec![0; 4]));
+        assert!(cache.get(Path::new("a"), "1").is_some());
+        cache.insert(Path::new("c"), "1", Arc::new(vec![0; 4]));
+        assert!(cache.get(Path::new("a"), "1").is_some());
+        assert!(cache.get(Path::new("b"), "1").is_none());
+        assert!(cache.get(Path::new("c"), "1").is_some());
+        assert!(cache.get(Path::new("c"), "2").is_none());
+    }
+}
@@ -1,9 +1,19 @@
+use std::env;
 use std::env::consts::OS;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use schemars::JsonSchema;
 use serde::Deserialize;
+use serde_json::Value;
 
+use crate::protocol::{AssetsConfig, ProtocolConfig};
+use crate::schema::check_unknown_keys;
+use crate::security::SecurityConfig;
 use crate::window::WindowAttributesConfig;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, Clone, Default)]
 pub struct Icons {
     pub linux: Option<String>,
     pub macos: Option<String>,
@@ -20,15 +30,150 @@ impl Icons {
         }
     }
 }
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, Clone)]
 pub struct Config {
+    #[serde(default)]
     pub build: Build,
+    #[serde(default)]
     pub package: Package,
+    #[serde(default)]
     pub pywui: Pywui,
-    pub icon: Icons
+    #[serde(default)]
+    pub icon: Icons,
+    pub protocols: Option<Vec<ProtocolConfig>>,
+    pub assets: Option<AssetsConfig>,
+    pub security: Option<SecurityConfig>,
+}
+
+/// Looked up in this order in each directory.
+pub const CONFIG_FILE_NAMES: [&str; 3] = ["pywui.conf.json", "pywui.conf.json5", "pywui.conf.toml"];
+
+impl Config {
+    /// Deserializes a parsed config, reporting where the first invalid value sits.
+    pub fn from_value(value: Value) -> Result<Self, ConfigError> {
+        serde_path_to_error::deserialize(value).map_err(|e| ConfigError {
+            file: None,
+            path: e.path().to_string(),
+            message: e.into_inner().to_string(),
+        })
+    }
+}
+
+/// A config value that does not fit the expected shape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// The config file, when the config was read from disk.
+    pub file: Option<PathBuf>,
+    /// Path of the offending value, such as `pywui.windows[0].width`, or `.` for the whole document.
+    pub path: String,
+    /// What was expected and what was found.
+    pub message: String,
+}
+
+impl ConfigError {
+    fn in_file(file: &Path, message: String) -> Self {
+        Self {
+            file: Some(file.to_path_buf()),
+            path: ".".to_string(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "Invalid config {} at `{}`: {}", file.display(), self.path, self.message),
+            None => write!(f, "Invalid config at `{}`: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Reads the config file at `path`, or the first one found walking up from the directory `path`,
+/// or from the working directory.
+///
+/// Relative paths in the config are made absolute against the config file's directory, and the
+/// result is checked against [`Config`] before it is returned. `strict` also rejects unknown keys.
+pub fn load_config_value(path: Option<&Path>, strict: bool) -> Result<(PathBuf, Value), ConfigError> {
+    let file = match path {
+        Some(path) if path.is_dir() => find_config(path),
+        Some(path) => Some(path.to_path_buf()),
+        None => env::current_dir().ok().and_then(|cwd| find_config(&cwd)),
+    };
+    let Some(file) = file else {
+        let place = path.map(|path| path.display().to_string()).unwrap_or_else(|| "the working directory".to_string());
+        return Err(ConfigError {
+            file: None,
+            path: ".".to_string(),
+            message: format!("no {} found in {} or its parents", CONFIG_FILE_NAMES.join(", "), place),
+        });
+    };
+    let text = fs::read_to_string(&file).map_err(|e| ConfigError::in_file(&file, e.to_string()))?;
+    let mut value = parse_config(&file, &text).map_err(|message| ConfigError::in_file(&file, message))?;
+    let base = file.parent().map(Path::to_path_buf).unwrap_or_default();
+    resolve_paths(&mut value, &fs::canonicalize(&base).unwrap_or(base));
+    let checked = match strict {
+        true => check_unknown_keys(&value),
+        false => Ok(()),
+    };
+    if let Err(e) = checked.and_then(|_| Config::from_value(value.clone()).map(drop)) {
+        return Err(ConfigError { file: Some(file), ..e });
+    }
+    Ok((file, value))
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// The first config file in `start` or its ancestors.
+pub fn find_config(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find_map(find_in_dir)
+}
+
+fn find_in_dir(dir: &Path) -> Option<PathBuf> {
+    CONFIG_FILE_NAMES.iter().map(|name| dir.join(name)).find(|file| file.is_file())
+}
+
+/// Picks the syntax from the extension; anything other than `.json5` and `.toml` is plain JSON.
+fn parse_config(file: &Path, text: &str) -> Result<Value, String> {
+    match file.extension().and_then(|ext| ext.to_str()) {
+        Some("json5") => json5::from_str(text).map_err(|e| e.to_string()),
+        Some("toml") => toml::from_str(text).map_err(|e| e.to_string().trim_end().to_string()),
+        _ => serde_json::from_str(text).map_err(|e| e.to_string()),
+    }
+}
+
+/// Makes the file system paths of a config absolute against `base`.
+fn resolve_paths(config: &mut Value, base: &Path) {
+    let Some(sections) = config.as_object_mut() else {
+        return;
+    };
+    let mut paths: Vec<&mut Value> = vec![];
+    for (section, value) in sections.iter_mut() {
+        match (section.as_str(), value) {
+            ("icon", Value::Object(icon)) => paths.extend(icon.values_mut()),
+            ("build", Value::Object(build)) => {
+                for (key, value) in build.iter_mut() {
+                    // `devPath` is usually a dev server url, but may name a directory.
+                    let is_dir = value.as_str().is_some_and(|path| !path.contains("://"));
+                    if key == "buildPath" || (key == "devPath" && is_dir) {
+                        paths.push(value);
+                    }
+                }
+            }
+            ("protocols", Value::Array(protocols)) => {
+                paths.extend(protocols.iter_mut().filter_map(|protocol| protocol.get_mut("root")));
+            }
+            _ => {}
+        }
+    }
+    for value in paths {
+        if let Some(path) = value.as_str().filter(|path| !path.is_empty() && Path::new(path).is_relative()) {
+            *value = Value::String(base.join(path).to_string_lossy().into_owned());
+        }
+    }
+}
+
+/// Every field is optional; commands left empty are skipped.
+#[derive(Debug, Deserialize, JsonSchema, Clone, Default)]
+#[serde(default)]
 pub struct Build {
     #[serde(rename = "beforeBuildCommand")]
     pub before_build_command: String,
@@ -38,17 +183,185 @@ pub struct Build {
 
     #[serde(rename = "devPath")]
     pub dev_path: String,
+
+    /// Frontend build directory or asset bundle served in production.
+    #[serde(rename = "buildPath")]
+    pub build_path: Option<String>,
+
+    pub mode: Option<RunMode>,
+
+    /// Seconds to wait for `devPath` to answer in dev mode.
+    #[serde(rename = "devServerTimeout")]
+    pub dev_server_timeout: Option<u64>,
+
+    /// Reloads webviews when files below a served directory change, in dev mode only.
+    #[serde(rename = "liveReload")]
+    pub live_reload: Option<bool>,
+}
+
+/// Environment variable that overrides the configured run mode.
+pub const MODE_ENV_VAR: &str = "PYWUI_MODE";
+
+/// Whether windows load the dev server or the bundled build.
+#[derive(Debug, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RunMode {
+    #[serde(alias = "development")]
+    Dev,
+    #[default]
+    #[serde(alias = "production")]
+    Prod,
+}
+
+impl RunMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunMode::Dev => "dev",
+            RunMode::Prod => "prod",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "dev" | "development" => Ok(RunMode::Dev),
+            "prod" | "production" => Ok(RunMode::Prod),
+            other => Err(format!("Unknown run mode '{}', expected 'dev' or 'prod'", other)),
+        }
+    }
+
+    /// The constructor argument wins over `PYWUI_MODE`, which wins over the config; production otherwise.
+    pub fn select(argument: Option<&str>, configured: Option<RunMode>) -> Result<Self, String> {
+        if let Some(argument) = argument {
+            return Self::parse(argument);
+        }
+        match env::var(MODE_ENV_VAR) {
+            Ok(value) if !value.trim().is_empty() => Self::parse(&value),
+            _ => Ok(configured.unwrap_or_default()),
+        }
+    }
 }
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, Clone, Default)]
+#[serde(default)]
 pub struct Package {
     #[serde(rename = "productName")]
     pub product_name: String,
     pub version: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, Clone, Default)]
 pub struct Pywui {
+    #[serde(default)]
     pub windows: Vec<WindowAttributesConfig>,
+    /// Number of threads that run Python commands and listeners.
+    pub workers: Option<usize>,
 }
 
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn selects_the_run_mode() {
+        assert_eq!(RunMode::select(Some("development"), Some(RunMode::Prod)), Ok(RunMode::Dev));
+        assert_eq!(RunMode::select(Some(" PROD "), Some(RunMode::Dev)), Ok(RunMode::Prod));
+        assert!(RunMode::select(Some("staging"), None).is_err());
+        let configured: RunMode = serde_json::from_str("\"production\"").unwrap();
+        assert_eq!(configured, RunMode::Prod);
+    }
+
+    fn config_error(value: Value) -> ConfigError {
+        Config::from_value(value).unwrap_err()
+    }
+
+    #[test]
+    fn loads_json5_and_toml_with_paths_relative_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let nested = root.join("app/src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            root.join("app/pywui.conf.json5"),
+            "{\n  // comment\n  build: {buildPath: 'dist', devPath: 'http://localhost:5173',},\n  icon: {linux: 'icons/app.png'},\n}",
+        )
+        .unwrap();
+
+        let file = find_config(&nested).unwrap();
+        assert_eq!(file, root.join("app/pywui.conf.json5"));
+        let (_, value) = load_config_value(Some(&file), false).unwrap();
+        assert_eq!(value["build"]["buildPath"], root.join("app/dist").to_string_lossy().as_ref());
+        assert_eq!(value["build"]["devPath"], "http://localhost:5173");
+        assert_eq!(value["icon"]["linux"], root.join("app/icons/app.png").to_string_lossy().as_ref());
+
+        let toml = root.join("pywui.conf.toml");
+        fs::write(&toml, "[build]\nbuildPath = '/srv/dist'\n\n[[protocols]]\nscheme = 'app'\nroot = 'public'\n").unwrap();
+        let config = Config::from_value(load_config_value(Some(&toml), false).unwrap().1).unwrap();
+        assert_eq!(config.build.build_path.as_deref(), Some("/srv/dist"));
+        let protocols = config.protocols.unwrap();
+        assert_eq!(protocols[0].root.as_deref(), Some(root.join("public").to_string_lossy().as_ref()));
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("pywui.conf.json");
+        fs::write(&file, "{\"build\": }").unwrap();
+        let error = load_config_value(Some(dir.path()), false).unwrap_err();
+        assert_eq!(error.file.as_deref(), Some(file.as_path()));
+        assert!(error.to_string().contains("expected value at line 1"));
+
+        fs::write(&file, "{\"pywui\": {\"workers\": -1}}").unwrap();
+        let error = load_config_value(Some(&file), false).unwrap_err();
+        assert_eq!(error.path, "pywui.workers");
+
+        fs::write(&file, "{\"pywui\": {\"worker\": 2}}").unwrap();
+        assert!(load_config_value(Some(&file), false).is_ok());
+        let error = load_config_value(Some(&file), true).unwrap_err();
+        assert_eq!(error.to_string(), format!(
+            "Invalid config {} at `pywui.worker`: unknown field `worker`, did you mean `workers`?",
+            file.display()
+        ));
+    }
+
+    #[test]
+    fn defaults_optional_sections() {
+        let config = Config::from_value(json!({})).unwrap();
+        assert!(config.build.before_dev_command.is_empty());
+        assert!(config.package.version.is_empty());
+        assert!(config.pywui.windows.is_empty());
+        assert_eq!(config.icon.get_for_current_os(), None);
+    }
+
+    #[test]
+    fn reports_wrong_types_with_the_value() {
+        let error = config_error(json!({"build": {"devServerTimeout": "soon"}}));
+        assert_eq!(error.path, "build.devServerTimeout");
+        assert_eq!(error.message, "invalid type: string \"soon\", expected u64");
+        assert_eq!(
+            error.to_string(),
+            "Invalid config at `build.devServerTimeout`: invalid type: string \"soon\", expected u64"
+        );
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        let error = config_error(json!({"protocols": [{"scheme": "app"}, {"host": "localhost"}]}));
+        assert_eq!(error.path, "protocols[1]");
+        assert_eq!(error.message, "missing field `scheme`");
+    }
 
+    #[test]
+    fn reports_unknown_variants() {
+        let error = config_error(json!({"build": {"mode": "staging"}}));
+        assert_eq!(error.path, "build.mode");
+        assert!(error.message.starts_with("unknown variant `staging`"));
+    }
+
+    #[test]
+    fn reports_a_document_that_is_not_an_object() {
+        let error = config_error(json!("pywui.conf.json"));
+        assert_eq!(error.path, ".");
+        assert_eq!(error.message, "invalid type: string \"pywui.conf.json\", expected struct Config");
+    }
+}
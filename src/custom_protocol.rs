@@ -0,0 +1,278 @@
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+
+use http::header::CONTENT_TYPE;
+use http::{HeaderName, HeaderValue, Response};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyBytes, PyDict, PyString, PyTuple};
+use wry::{http::Request, RequestAsyncResponder};
+
+use crate::asyncio::AsyncioRunner;
+use crate::security::SecurityHeaders;
+
+/// A Python callback serving a whole custom scheme, or the paths below `prefix`.
+#[derive(Clone)]
+pub struct PyProtocolHandler {
+    /// `None` routes requests of the file-serving protocols.
+    pub scheme: Option<String>,
+    pub prefix: String,
+    pub callback: Arc<PyObject>,
+}
+
+impl PyProtocolHandler {
+    pub fn new(scheme: Option<String>, prefix: Option<String>, callback: PyObject) -> PyResult<Self> {
+        if scheme.is_none() && prefix.is_none() {
+            return Err(PyValueError::new_err("A protocol handler needs a scheme, a prefix or both"));
+        }
+        let scheme = scheme.map(|scheme| scheme.to_ascii_lowercase());
+        if let Some(scheme) = &scheme {
+            let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+            if !valid {
+                return Err(PyValueError::new_err(format!("'{}' is not a valid scheme", scheme)));
+            }
+        }
+        let prefix = prefix.unwrap_or_else(|| "/".to_string());
+        if !prefix.starts_with('/') {
+            return Err(PyValueError::new_err(format!("Route prefix '{}' must start with '/'", prefix)));
+        }
+        Ok(Self {
+            scheme,
+            prefix,
+            callback: Arc::new(callback),
+        })
+    }
+
+    fn matches(&self, scheme: &str, serves_files: bool, path: &str) -> bool {
+        let scheme_matches = match &self.scheme {
+            Some(own) => own == scheme,
+            None => serves_files,
+        };
+        // `/api` covers `/api` and `/api/…` but not `/apiary`.
+        let prefix_matches = match path.strip_prefix(&self.prefix) {
+            Some(rest) => self.prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        };
+        scheme_matches && prefix_matches
+    }
+}
+
+/// Picks the handler with the longest matching prefix; `serves_files` tells whether `scheme` is a file protocol.
+pub fn find_handler<'a>(
+    handlers: &'a [PyProtocolHandler],
+    scheme: &str,
+    serves_files: bool,
+    path: &str,
+) -> Option<&'a PyProtocolHandler> {
+    handlers
+        .iter()
+        .filter(|handler| handler.matches(scheme, serves_files, path))
+        .max_by_key(|handler| handler.prefix.len())
+}
+
+/// Calls `callback` with the request and answers with what it returns, awaiting coroutines on `runner`.
+///
+/// Meant to run on a worker thread; the responder completes the request from whichever thread finishes.
+pub fn respond(
+    callback: &PyObject,
+    request: Request<Vec<u8>>,
+    responder: RequestAsyncResponder,
+    runner: &AsyncioRunner,
+    security: Arc<SecurityHeaders>,
+) {
+    let path = request.uri().path().to_string();
+    let finish = Arc::new(move |py: Python, result: PyResult<PyObject>| {
+        let mut response = to_response(py, result);
+        security.apply(&path, &mut response);
+        response
+    });
+    Python::with_gil(|py| {
+        let result = request_to_py(py, &request).and_then(|args| callback.call1(py, (args,)));
+        match result {
+            Ok(value) if runner.is_awaitable(py, &value) => {
+                let responder = Arc::new(Mutex::new(Some(responder)));
+                let loop_responder = responder.clone();
+                let loop_finish = finish.clone();
+                let submitted = runner.submit(py, value, move |py, result| {
+                    if let Some(responder) = loop_responder.lock().unwrap().take() {
+                        responder.respond(loop_finish(py, result));
+                    }
+                });
+                if let Err(e) = submitted {
+                    if let Some(responder) = responder.lock().unwrap().take() {
+                        responder.respond(finish(py, Err(e)));
+                    }
+                }
+            }
+            result => responder.respond(finish(py, result)),
+        }
+    });
+}
+
+fn to_response(py: Python, result: PyResult<PyObject>) -> Response<Cow<'static, [u8]>> {
+    match result.and_then(|value| response_from_py(py, value.bind(py))) {
+        Ok(response) => response,
+        Err(e) => {
+            e.print(py);
+            error_response(500, "Protocol handler failed")
+        }
+    }
+}
+
+fn error_response(status: u16, body: &'static str) -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain")
+        .body(Cow::Borrowed(body.as_bytes()))
+        .unwrap()
+}
+
+/// `{"method", "uri", "path", "query", "headers", "body"}` with lowercase header names and `bytes` body.
+fn request_to_py(py: Python, request: &Request<Vec<u8>>) -> PyResult<PyObject> {
+    let headers = PyDict::new(py);
+    for name in request.headers().keys() {
+        let values: Vec<&str> = request
+            .headers()
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        headers.set_item(name.as_str(), values.join(", "))?;
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("method", request.method().as_str())?;
+    dict.set_item("uri", request.uri().to_string())?;
+    dict.set_item("path", request.uri().path())?;
+    dict.set_item("query", request.uri().query().unwrap_or_default())?;
+    dict.set_item("headers", headers)?;
+    dict.set_item("body", PyBytes::new(py, request.body()))?;
+    Ok(dict.into_any().unbind())
+}
+
+/// Accepts `(status, headers, body)`, a dict with those keys, or a bare body answered with 200.
+/// `None` means the handler had nothing to serve.
+fn response_from_py(py: Python, value: &Bound<PyAny>) -> PyResult<Response<Cow<'static, [u8]>>> {
+    if value.is_none() {
+        return Ok(error_response(404, "Not found"));
+    }
+    let (status, headers, body) = if let Ok(tuple) = value.downcast::<PyTuple>() {
+        let (status, headers, body): (u16, Option<Bound<PyDict>>, Bound<PyAny>) = tuple.extract()?;
+        (status, headers, body)
+    } else if let Ok(dict) = value.downcast::<PyDict>() {
+        let status = match dict.get_item("status")? {
+            Some(status) => status.extract::<u16>()?,
+            None => 200,
+        };
+        let headers = match dict.get_item("headers")? {
+            Some(headers) if !headers.is_none() => Some(headers.downcast_into::<PyDict>()?),
+            _ => None,
+        };
+        let body = dict.get_item("body")?.unwrap_or_else(|| py.None().into_bound(py));
+        (status, headers, body)
+    } else {
+        (200, None, value.clone())
+    };
+
+    let mut response = Response::builder().status(status);
+    let mut has_content_type = false;
+    for (name, value) in headers.iter().flat_map(|headers| headers.iter()) {
+        let name = HeaderName::try_from(name.extract::<String>()?)
+            .map_err(|e| PyValueError::new_err(format!("Invalid header name: {}", e)))?;
+        let value = HeaderValue::try_from(value.str()?.to_string())
+            .map_err(|e| PyValueError::new_err(format!("Invalid value for header '{}': {}", name, e)))?;
+        has_content_type |= name == CONTENT_TYPE;
+        response = response.header(name, value);
+    }
+    let body: Vec<u8> = if body.is_none() {
+        vec![]
+    } else if let Ok(bytes) = body.downcast::<PyBytes>() {
+        bytes.as_bytes().to_vec()
+    } else if let Ok(bytes) = body.downcast::<PyByteArray>() {
+        bytes.to_vec()
+    } else if let Ok(text) = body.downcast::<PyString>() {
+        if !has_content_type {
+            response = response.header(CONTENT_TYPE, "text/plain; charset=utf-8");
+        }
+        text.to_str()?.as_bytes().to_vec()
+    } else {
+        return Err(PyTypeError::new_err(format!(
+            "Protocol handler body must be bytes or str, not {}",
+            body.get_type().name()?
+        )));
+    };
+    response
+        .body(Cow::Owned(body))
+        .map_err(|e| PyValueError::new_err(format!("Invalid protocol response: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handler(scheme: Option<&str>, prefix: Option<&str>) -> PyResult<PyProtocolHandler> {
+        pyo3::prepare_freethreaded_python();
+        let callback = Python::with_gil(|py| py.None());
+        PyProtocolHandler::new(scheme.map(str::to_string), prefix.map(str::to_string), callback)
+    }
+
+    fn respond_with(code: &std::ffi::CStr) -> PyResult<Response<Cow<'static, [u8]>>> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| response_from_py(py, &py.eval(code, None, None)?))
+    }
+
+    #[test]
+    fn validates_schemes_and_prefixes() {
+        let route = handler(None, Some("/api")).unwrap();
+        assert_eq!((route.scheme, route.prefix.as_str()), (None, "/api"));
+        let scheme = handler(Some("App+V2"), None).unwrap();
+        assert_eq!((scheme.scheme.as_deref(), scheme.prefix.as_str()), (Some("app+v2"), "/"));
+
+        assert!(handler(None, None).is_err());
+        assert!(handler(Some("2app"), None).is_err());
+        assert!(handler(Some("my app"), None).is_err());
+        assert!(handler(Some("app"), Some("api")).is_err());
+    }
+
+    #[test]
+    fn finds_the_longest_matching_prefix() {
+        let handlers = [
+            handler(None, Some("/api")).unwrap(),
+            handler(None, Some("/api/users/")).unwrap(),
+            handler(Some("data"), None).unwrap(),
+        ];
+        let prefix = |scheme: &str, serves_files: bool, path: &str| {
+            find_handler(&handlers, scheme, serves_files, path).map(|handler| handler.prefix.as_str())
+        };
+
+        assert_eq!(prefix("pywui", true, "/api"), Some("/api"));
+        assert_eq!(prefix("pywui", true, "/api/items"), Some("/api"));
+        assert_eq!(prefix("pywui", true, "/api/users/1"), Some("/api/users/"));
+        assert_eq!(prefix("pywui", true, "/api/users"), Some("/api"));
+        assert_eq!(prefix("pywui", true, "/apiary"), None);
+        assert_eq!(prefix("media", false, "/api"), None);
+        assert_eq!(prefix("data", false, "/anything"), Some("/"));
+    }
+
+    #[test]
+    fn converts_python_responses() {
+        let tuple = respond_with(c"(201, {'X-Id': 7}, b'made')").unwrap();
+        assert_eq!(tuple.status(), 201);
+        assert_eq!(tuple.headers()["x-id"], "7");
+        assert_eq!(tuple.body().as_ref(), b"made");
+
+        let dict = respond_with(c"{'headers': {'Content-Type': 'text/html'}, 'body': '<p>'}").unwrap();
+        assert_eq!(dict.status(), 200);
+        assert_eq!(dict.headers()[CONTENT_TYPE], "text/html");
+        assert_eq!(dict.body().as_ref(), b"<p>");
+
+        let text = respond_with(c"'hello'").unwrap();
+        assert_eq!(text.headers()[CONTENT_TYPE], "text/plain; charset=utf-8");
+        assert_eq!(respond_with(c"bytearray(b'raw')").unwrap().body().as_ref(), b"raw");
+        assert_eq!(respond_with(c"None").unwrap().status(), 404);
+
+        assert!(respond_with(c"42").is_err());
+        assert!(respond_with(c"(200, {'bad header': 'x'}, b'')").is_err());
+        assert!(respond_with(c"(1000, None, b'')").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http::Uri;
+
+/// How long the dev server may take to come up when the config does not say.
+pub const DEFAULT_DEV_SERVER_TIMEOUT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The `beforeDevCommand` process, whose output is streamed into our logs.
+pub struct DevServer {
+    child: Child,
+}
+
+impl DevServer {
+    /// Runs `command` through the platform shell in its own process group.
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        println!("Running beforeDevCommand: {}", command);
+        let mut child = shell(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, false);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, true);
+        }
+        Ok(Self { child })
+    }
+
+    /// Whether the command already exited, which means the dev server will never answer.
+    pub fn has_exited(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(Some(_)))
+    }
+
+    /// Terminates the command and everything it started.
+    pub fn kill(&mut self) {
+        let pid = self.child.id().to_string();
+        // The group outlives the shell when it backgrounded the server, so it is signalled either way.
+        #[cfg(unix)]
+        let signalled = Command::new("kill")
+            .args(["-TERM", "--", &format!("-{}", pid)])
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        // The tree is found through the parent pid, which may already belong to another process.
+        #[cfg(windows)]
+        let signalled = !self.has_exited()
+            && Command::new("taskkill")
+                .args(["/T", "/F", "/PID", &pid])
+                .status()
+                .is_ok_and(|status| status.success());
+        if !signalled && !self.has_exited() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for DevServer {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command).process_group(0);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+fn forward_lines<R: Read + Send + 'static>(output: R, is_stderr: bool) {
+    thread::Builder::new()
+        .name("pywui-dev-server-output".to_string())
+        .spawn(move || {
+            for line in BufReader::new(output).lines().map_while(Result::ok) {
+                if is_stderr {
+                    eprintln!("[dev] {}", line);
+                } else {
+                    println!("[dev] {}", line);
+                }
+            }
+        })
+        .expect("Failed to spawn dev server output thread");
+}
+
+/// Polls `url` until it accepts a request, giving up after `timeout` or once `server` exits.
+pub fn wait_for(url: &str, timeout: Duration, mut server: Option<&mut DevServer>) -> Result<(), String> {
+    let uri: Uri = url.parse().map_err(|_| format!("devPath '{}' is not a valid url", url))?;
+    let host = uri.host().ok_or_else(|| format!("devPath '{}' has no host", url))?.to_string();
+    let is_https = uri.scheme_str() == Some("https");
+    let port = uri.port_u16().unwrap_or(if is_https { 443 } else { 80 });
+    let deadline = Instant::now() + timeout;
+    println!("Waiting for the dev server at {}", url);
+    loop {
+        // Only plain http can be probed with a request; for https an open port has to do.
+        let ready = match is_https {
+            true => connect(&host, port).is_some(),
+            false => responds(&host, port, uri.path()),
+        };
+        if ready {
+            return Ok(());
+        }
+        if server.as_mut().is_some_and(|server| server.has_exited()) {
+            return Err("beforeDevCommand exited before the dev server answered".to_string());
+        }
+        if Instant::now() >= deadline {
+            return Err(format!("Dev server at {} did not answer within {}s", url, timeout.as_secs()));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn connect(host: &str, port: u16) -> Option<TcpStream> {
+    (host, port)
+        .to_socket_addrs()
+        .ok()?
+        .find_map(|addr| TcpStream::connect_timeout(&addr, POLL_INTERVAL).ok())
+}
+
+fn responds(host: &str, port: u16, path: &str) -> bool {
+    let Some(mut stream) = connect(host, port) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+    let request = format!("HEAD {} HTTP/1.1\r\nHost: {}:{}\r\nConnection: close\r\n\r\n", path, host, port);
+    if stream.write_all(request.as_bytes()).is_err() {
+        return false;
+    }
+    let mut status_line = [0u8; 5];
+    stream.read_exact(&mut status_line).is_ok() && &status_line == b"HTTP/"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn waits_until_the_server_answers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 64];
+            let _ = stream.read(&mut request);
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        });
+        assert!(wait_for(&format!("http://127.0.0.1:{}/", port), Duration::from_secs(5), None).is_ok());
+    }
+
+    #[test]
+    fn gives_up_after_the_timeout() {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let result = wait_for(&format!("http://127.0.0.1:{}/", port), Duration::from_millis(300), None);
+        assert!(result.unwrap_err().contains("did not answer"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kills_the_command() {
+        let mut server = DevServer::spawn("sleep 30").unwrap();
+        assert!(!server.has_exited());
+        server.kill();
+        assert!(server.has_exited());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kills_processes_left_behind_by_the_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let mut server = DevServer::spawn(&format!("sleep 30 & echo $! > '{}'", pid_file.display())).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !server.has_exited() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        let pid = std::fs::read_to_string(&pid_file).unwrap().trim().to_string();
+        let is_alive = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", pid))
+                .is_ok_and(|stat| stat.rsplit(')').next().is_some_and(|rest| !rest.trim_start().starts_with('Z')))
+        };
+        assert!(is_alive());
+
+        server.kill();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while is_alive() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!is_alive());
+    }
+}
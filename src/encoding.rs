@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Text bodies smaller than this gain little from compression.
+const DEFAULT_MIN_SIZE: u64 = 1024;
+
+/// How asset responses are compressed.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct CompressionConfig {
+    /// Serve `app.js.br` / `app.js.gz` in place of `app.js` when the client accepts them. Defaults to true.
+    pub precompressed: Option<bool>,
+    /// Gzip textual assets without a precompressed sibling while serving them. Defaults to false.
+    pub gzip: Option<bool>,
+    /// Smallest body, in bytes, compressed on the fly.
+    #[serde(rename = "minSize")]
+    pub min_size: Option<u64>,
+}
+
+impl CompressionConfig {
+    pub fn precompressed(&self) -> bool {
+        self.precompressed.unwrap_or(true)
+    }
+
+    pub fn gzip(&self) -> bool {
+        self.gzip.unwrap_or(false)
+    }
+
+    pub fn min_size(&self) -> u64 {
+        self.min_size.unwrap_or(DEFAULT_MIN_SIZE)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// Server preference, best compression first.
+    const ALL: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+    /// Value of the `Content-Encoding` header.
+    pub fn token(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// Suffix of precompressed sibling files.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+}
+
+/// Encodings allowed by an `Accept-Encoding` header, in server preference order.
+pub fn accepted_encodings(header: Option<&str>) -> Vec<Encoding> {
+    let Some(header) = header else {
+        return vec![];
+    };
+    let mut wildcard = None;
+    let mut explicit = vec![];
+    for item in header.split(',') {
+        let mut params = item.split(';');
+        let coding = params.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match coding.as_str() {
+            "*" => wildcard = Some(quality > 0.0),
+            "x-gzip" => explicit.push(("gzip".to_string(), quality > 0.0)),
+            _ => explicit.push((coding, quality > 0.0)),
+        }
+    }
+    Encoding::ALL
+        .into_iter()
+        .filter(|encoding| {
+            explicit
+                .iter()
+                .find(|(coding, _)| coding == encoding.token())
+                .map(|(_, accepted)| *accepted)
+                .or(wildcard)
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+pub fn gzip(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 3), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Entity tag of an encoded representation, distinct from the identity one as RFC 9110 requires.
+pub fn encoded_entity_tag(etag: &str, encoding: Encoding) -> String {
+    format!("{}-{}\"", etag.trim_end_matches('"'), encoding.extension())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    #[test]
+    fn negotiates_accept_encoding() {
+        use Encoding::{Brotli, Gzip};
+        assert_eq!(accepted_encodings(None), vec![]);
+        assert_eq!(accepted_encodings(Some("gzip, deflate, br")), vec![Brotli, Gzip]);
+        assert_eq!(accepted_encodings(Some("gzip;q=0.5, br;q=0")), vec![Gzip]);
+        assert_eq!(accepted_encodings(Some("x-gzip")), vec![Gzip]);
+        assert_eq!(accepted_encodings(Some("*;q=0.1, gzip;q=0")), vec![Brotli]);
+        assert_eq!(accepted_encodings(Some("identity")), vec![]);
+    }
+
+    #[test]
+    fn gzips_round_trip() {
+        let data = "body { color: red; }\n".repeat(100);
+        let compressed = gzip(data.as_bytes()).unwrap();
+        assert!(compressed.len() < data.len());
+        let mut decoded = String::new();
+        GzDecoder::new(compressed.as_slice()).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn tags_encoded_representations() {
+        assert_eq!(encoded_entity_tag("\"1f-2a\"", Encoding::Gzip), "\"1f-2a-gz\"");
+    }
+}
@@ -0,0 +1,64 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed set of threads that run jobs away from the event loop thread.
+pub struct WorkerPool {
+    sender: Sender<Job>,
+}
+
+impl WorkerPool {
+    pub fn new(name: &str, size: usize) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..size.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("{}-{}", name, index))
+                .spawn(move || loop {
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    // A panicking job must not take the thread down with it, or the pool slowly runs dry.
+                    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        eprintln!("A job on {} panicked", thread::current().name().unwrap_or("a worker"));
+                    }
+                })
+                .expect("Failed to spawn worker thread");
+        }
+        Self { sender }
+    }
+
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        if self.sender.send(Box::new(job)).is_err() {
+            eprintln!("Worker pool is shut down, dropping job");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn keeps_running_jobs_after_a_panic() {
+        let pool = WorkerPool::new("pywui-test", 1);
+        let (sender, receiver) = channel();
+        for index in 0..3 {
+            let sender = sender.clone();
+            pool.execute(move || {
+                if index < 2 {
+                    panic!("job {} failed", index);
+                }
+                sender.send(index).unwrap();
+            });
+        }
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(2));
+    }
+}
@@ -1,25 +1,57 @@
-window.__pywui__ = {
-    invoke(command, args = [], timeout = 5000) {
-        return new Promise((resolve, reject) => {
+(function (info) {
+    class PywuiError extends Error {
+        constructor(error, command) {
+            super(error && error.message ? error.message : String(error));
+            this.name = "PywuiError";
+            this.type = error && error.type ? error.type : "Error";
+            this.traceback = error && error.traceback ? error.traceback : null;
+            this.command = command;
+        }
+    }
+
+    window.__pywui__ = {
+        version: info.version,
+        protocol: info.protocol,
+        features: info.features.slice(),
+        mode: info.mode,
+        supports(feature) {
+            return info.features.includes(feature);
+        },
+        PywuiError,
+        invoke(command, args = [], timeout = 5000) {
+            return new Promise((resolve, reject) => {
+                const request_id = `req_${ Date.now() }_${ Math.random() }`;
+                const message = { event_type: "request", command, args, request_id };
+                const onResponse = (ev) => {
+                    if (ev.detail) {
+                        const error = ev.detail["error"];
+                        const result = ev.detail["data"];
+                        clearTimeout(timer);
+                        window.removeEventListener(request_id, onResponse);
+                        if (error) reject(new PywuiError(error, command));
+                        else resolve(result);
+                    }
+                };
+                const timer = setTimeout(() => {
+                    window.removeEventListener(request_id, onResponse);
+                    reject(new PywuiError({
+                        type: "TimeoutError",
+                        message: `Command '${ command }' timed out after ${ timeout }ms`
+                    }, command));
+                }, timeout);
+                window.addEventListener(request_id, onResponse);
+                window.ipc.postMessage(JSON.stringify(message));
+            });
+        },
+        emit(command, args=[]){
             const request_id = `req_${ Date.now() }_${ Math.random() }`;
-            const message = { event_type: "request", command, args, request_id };
-            const timer = setTimeout(() => reject(new Error("Timeout")), timeout);
-            window.addEventListener(request_id, (ev) => {
-                if (ev.detail) {
-                    const error = ev.detail["error"];
-                    const result = ev.detail["data"];
-                    clearTimeout(timer);
-                    window.removeEventListener(request_id, () => {})
-                    if (error) reject(new Error(error));
-                    else resolve(result);
-                }
-            })
+            const message = { event_type: "event", command, args, request_id };
             window.ipc.postMessage(JSON.stringify(message));
-        });
-    },
-    emit(command, args=[]){
-        const request_id = `req_${ Date.now() }_${ Math.random() }`;
-        const message = { event_type: "event", command, args, request_id };
-        window.ipc.postMessage(JSON.stringify(message));
-    }
-};
+        },
+        listen(event, handler) {
+            const listener = (ev) => handler(ev.detail ? ev.detail["data"] : undefined, ev);
+            window.addEventListener(event, listener);
+            return () => window.removeEventListener(event, listener);
+        }
+    };
+})(__PYWUI_BRIDGE_INFO__);
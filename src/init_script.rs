@@ -1,30 +1,19 @@
-pub fn get_init_script() -> &'static str {
-    r#"
-        window.__pywui__ = {
-            invoke(command, args = [], timeout = 5000) {
-                return new Promise((resolve, reject) => {
-                    const request_id = `req_${ Date.now() }_${ Math.random() }`;
-                    const message = { event_type: "request", command, args, request_id };
-                    const timer = setTimeout(() => reject(new Error("Timeout")), timeout);
-                    window.addEventListener(request_id, (ev) => {
-                        if (ev.detail) {
-                            const error = ev.detail["error"];
-                            const result = ev.detail["data"];
-                            clearTimeout(timer);
-                            window.removeEventListener(request_id, () => {})
-                            if (error) reject(new Error(error));
-                            else resolve(result);
-                        }
-                    })
-                    window.ipc.postMessage(JSON.stringify(message));
-                });
-            },
-            emit(command, args=[]){
-                const request_id = `req_${ Date.now() }_${ Math.random() }`;
-                const message = { event_type: "event", command, args, request_id };
-                window.ipc.postMessage(JSON.stringify(message));
-            }
-        };
+use serde_json::json;
 
-    "#
+/// Version of the message format spoken between the bridge and `WindowManager`.
+/// Bump it whenever a change would break a frontend written against the previous one.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Capabilities a frontend can probe with `window.__pywui__.supports(name)`.
+const FEATURES: &[&str] = &["invoke", "emit", "listen", "structured-errors", "coroutine-commands"];
+
+/// The bridge script, carrying the run mode of this run.
+pub fn get_init_script(mode: &str) -> String {
+    let info = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "protocol": PROTOCOL_VERSION,
+        "features": FEATURES,
+        "mode": mode,
+    });
+    include_str!("init_script.js").replace("__PYWUI_BRIDGE_INFO__", &info.to_string())
 }
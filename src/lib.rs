@@ -1,13 +1,17 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::env::consts::OS;
 use std::fs;
 use std::io::Write;
 use std::ops::Deref;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 
-use http::header::CONTENT_TYPE;
-use http::Response;
 use image::EncodableLayout;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{PyFunction, PyTuple};
 use serde::{Deserialize, Serialize};
@@ -25,10 +29,18 @@ use wry::{
 };
 use wry::WebViewAttributes;
 
-use crate::config::Config;
-use crate::init_script::get_init_script;
-use crate::util::{json_to_py, load_py_config, py_to_json};
-use crate::window::WindowAttributesConfig;
+use crate::asyncio::AsyncioRunner;
+use crate::config::{Config, RunMode};
+use crate::dev_server::{wait_for, DevServer, DEFAULT_DEV_SERVER_TIMEOUT};
+use crate::custom_protocol::{find_handler, PyProtocolHandler};
+use crate::executor::WorkerPool;
+use crate::live_reload::AssetChange;
+use crate::protocol::Protocols;
+use crate::security::SecurityHeaders;
+use crate::init_script::{get_init_script, PROTOCOL_VERSION};
+use crate::util::{json_to_py, load_py_config, py_err_to_json, py_to_json};
+use crate::webview::{wrap_script, WebViewUpdate};
+use crate::window::{WindowAttributesConfig, WindowUpdate};
 
 fn main(){
 
@@ -39,86 +51,56 @@ mod util;
 mod webview;
 mod window;
 mod init_script;
+mod executor;
+mod asyncio;
+mod protocol;
+mod range;
+mod cache;
+mod bundle;
+mod encoding;
+mod custom_protocol;
+mod security;
+mod dev_server;
+mod live_reload;
+mod schema;
+
+create_exception!(pywui_rs, ConfigError, PyValueError, "The config does not have the expected shape; `path` locates the offending value.");
+
+fn config_error(error: config::ConfigError) -> PyErr {
+    Python::with_gil(|py| {
+        let err = ConfigError::new_err(error.to_string());
+        let _ = err.value(py).setattr("path", error.path);
+        let _ = err.value(py).setattr("file", error.file);
+        err
+    })
+}
 
-fn get_wry_response(
-    request: Request<Vec<u8>>,
-    responder: RequestAsyncResponder,
-    base_path: &PathBuf,
-) {
-    let path = request.uri().path();
-    let relative_path = if path == "/" {
-        "index.html"
-    } else {
-        &path[1..]
+/// Reads the window icon for this platform from the config, if it names one.
+fn load_icon(config: &Config) -> Result<Option<Icon>, config::ConfigError> {
+    let Some(path) = config.icon.get_for_current_os() else {
+        return Ok(None);
     };
-
-    let file_path = base_path.join(relative_path);
-    println!("Assets:: {}", request.uri());
-    if !file_path.exists() {
-        responder.respond(
-            Response::builder()
-                .header(CONTENT_TYPE, "text/plain")
-                .status(404)
-                .body(b"File not found".to_vec())
-                .unwrap(),
-        );
-        return;
-    }
-
-    match fs::read(&file_path) {
-        Ok(content) => {
-            let mimetype = match file_path.extension().and_then(|ext| ext.to_str()) {
-                Some("html") => "text/html",
-                Some("js") => "text/javascript",
-                Some("css") => "text/css",
-                Some("png") => "image/png",
-                Some("jpg") | Some("jpeg") => "image/jpeg",
-                Some("gif") => "image/gif",
-                Some("wasm") => "application/wasm",
-                Some("json") => "application/json",
-                _ => "application/octet-stream",
-            };
-
-            responder.respond(
-                Response::builder()
-                    .header(CONTENT_TYPE, mimetype)
-                    .status(200)
-                    .body(content)
-                    .unwrap(),
-            );
-        }
-        Err(_) => {
-            responder.respond(
-                Response::builder()
-                    .header(CONTENT_TYPE, "text/plain")
-                    .status(500)
-                    .body(b"Failed to read the file".to_vec())
-                    .unwrap(),
-            );
-        }
-    }
+    let error = |message: String| config::ConfigError {
+        file: None,
+        path: format!("icon.{}", OS),
+        message,
+    };
+    let bytes = fs::read(&path).map_err(|e| error(format!("cannot read '{}': {}", path, e)))?;
+    let image = image::load_from_memory(bytes.as_bytes())
+        .map_err(|e| error(format!("cannot decode '{}': {}", path, e)))?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(image.into_raw(), width, height)
+        .map(Some)
+        .map_err(|e| error(format!("invalid icon '{}': {}", path, e)))
 }
 
 fn create_new_window(
     webview: WebViewAttributes,
     window: WindowAttributesConfig,
     event_loop: &EventLoopWindowTarget<UserEvent>,
-    icon: Option<String>,
+    icon: Option<Icon>,
 ) -> (Window, WebView) {
-    println!("Image path {}", icon.clone().unwrap());
-    let icon = {
-        let bytes = match fs::read(format!("{}", icon.unwrap_or("pywui.png".to_string()).to_string())) {
-            Ok(data) => data,
-            Err(e) => panic!("Failed to read the file: {}", e),
-        };
-        let image = match image::load_from_memory(bytes.as_bytes()) {
-            Ok(img) => img.into_rgba8(),
-            Err(e) => panic!("Failed to decode image: {}", e),
-        };
-        let (width, height) = image.dimensions();
-        let rgba = image.into_raw();
-        Icon::from_rgba(rgba, width, height).unwrap()
-    };
     let app = WindowBuilder::new()
         .with_title(window.title.unwrap_or("Window".to_string()))
         .with_inner_size(LogicalSize {
@@ -136,17 +118,17 @@ fn create_new_window(
         .with_focused(window.focused.unwrap_or(true))
         .with_resizable(window.resizable.unwrap_or(true))
         .with_visible(window.visible.unwrap_or(true))
-        .with_window_icon(Some(icon))
+        .with_window_icon(icon)
         .build(event_loop)
         .unwrap();
     let builder = WebViewBuilder::with_attributes(webview);
     #[cfg(not(target_os = "linux"))]
-    let webview = builder.with_initialization_script(get_init_script()).build(&app).unwrap();
+    let webview = builder.build(&app).unwrap();
     #[cfg(target_os = "linux")]
     let webview = {
         use tao::platform::unix::WindowExtUnix;
         use wry::WebViewBuilderExtUnix;
-        let vbox = window.default_vbox().unwrap();
+        let vbox = app.default_vbox().unwrap();
         builder.build_gtk(vbox).unwrap()
     };
     (app, webview)
@@ -158,7 +140,9 @@ enum UserEvent {
     Emit(EmitData),
     Exit(),
     Close(WindowId),
-    UpdateWindow(String, Value, bool),
+    UpdateWindow(String, WindowUpdate),
+    UpdateWebview(String, WebViewUpdate, Option<PyObject>),
+    AssetsChanged(AssetChange),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -169,19 +153,73 @@ struct IPCData {
     args: Value,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug)]
 struct ResponseData {
+    window_id: WindowId,
     request_id: String,
     data: Box<Value>,
+    error: Option<Value>,
+}
+
+/// Turns the outcome of a command into the response for the webview that invoked it.
+fn command_response(py: Python, window_id: WindowId, request_id: String, result: PyResult<PyObject>) -> ResponseData {
+    match result.and_then(|value| py_to_json(py, value)) {
+        Ok(value) => ResponseData {
+            window_id,
+            request_id,
+            data: Box::new(value),
+            error: None,
+        },
+        Err(err) => ResponseData {
+            window_id,
+            request_id,
+            data: Box::new(Value::Null),
+            error: Some(py_err_to_json(py, &err)),
+        },
+    }
+}
+
+fn send_response(proxy: &EventLoopProxy<UserEvent>, response: ResponseData) {
+    if proxy.send_event(UserEvent::Response(response)).is_err() {
+        eprintln!("Dropped IPC response, the event loop is closed");
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct EmitData {
     event: String,
     data: Box<Value>,
+    target: EmitTarget,
+}
+
+/// Which windows receive an emitted event.
+#[derive(Debug, Serialize, Deserialize)]
+enum EmitTarget {
+    All,
+    Only(Vec<String>),
+    Except(Vec<String>),
+}
+
+impl EmitTarget {
+    fn includes(&self, label: &str) -> bool {
+        match self {
+            EmitTarget::All => true,
+            EmitTarget::Only(labels) => labels.iter().any(|l| l == label),
+            EmitTarget::Except(labels) => !labels.iter().any(|l| l == label),
+        }
+    }
+}
+
+thread_local! {
+    /// The event loop lives on the thread that created the manager until `run()` takes it, which
+    /// keeps `WindowManager` itself free of thread-bound state. It is tagged with the id of the
+    /// manager that owns it, and a thread holds at most one.
+    static EVENT_LOOP: RefCell<Option<(u64, EventLoop<UserEvent>)>> = const { RefCell::new(None) };
 }
 
-#[pyclass(unsendable)]
+static NEXT_MANAGER_ID: AtomicU64 = AtomicU64::new(0);
+
+#[pyclass]
 struct WindowManager {
     webviews: Arc<Mutex<HashMap<String, WindowId>>>,
     command: Arc<Mutex<Py<PyFunction>>>,
@@ -189,11 +227,25 @@ struct WindowManager {
     on_start: Arc<Mutex<Py<PyFunction>>>,
     on_stop: Arc<Mutex<Py<PyFunction>>>,
     config: Arc<Mutex<Config>>,
-    proxy: Option<Arc<Mutex<EventLoopProxy<UserEvent>>>>,
-    base_path: PathBuf,
-    webview_windows: Arc<Mutex<HashMap<WindowId, (Window, WebView, String)>>>,
-    event_loop: EventLoop<UserEvent>,
+    /// `None` until an event loop is attached.
+    proxy: Arc<Mutex<Option<EventLoopProxy<UserEvent>>>>,
+    protocols: Arc<Protocols>,
+    protocol_handlers: Arc<Mutex<Vec<PyProtocolHandler>>>,
+    security: Arc<SecurityHeaders>,
+    mode: RunMode,
+    running: AtomicBool,
+    id: u64,
+}
 
+impl Drop for WindowManager {
+    /// Frees the thread's event loop slot when a manager that never ran goes away.
+    fn drop(&mut self) {
+        let _ = EVENT_LOOP.try_with(|cell| {
+            if let Ok(mut slot) = cell.try_borrow_mut() {
+                slot.take_if(|(id, _)| *id == self.id);
+            }
+        });
+    }
 }
 
 fn find_by_label<'a>(
@@ -203,56 +255,225 @@ fn find_by_label<'a>(
     map.iter().find(|(_, (_, _, lbl))| lbl == label)
 }
 
-#[pymethods]
+/// Moves values that are only ever used on the event loop thread into `allow_threads`.
+struct AssertSend<T>(T);
+
+// SAFETY: the wrapped values are created on the event loop thread and `event_loop.run`
+// never returns, so they are never touched from another thread.
+unsafe impl<T> Send for AssertSend<T> {}
+
+impl<T> AssertSend<T> {
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Builds the script that fires a `CustomEvent` named `event` on the webview's `window`.
+fn dispatch_event_script(event: &str, detail: &Value) -> String {
+    format!(
+        "window.dispatchEvent(new CustomEvent({}, {{ detail: {} }}));",
+        Value::String(event.to_string()),
+        detail
+    )
+}
+
+/// Reports the outcome of an `update_webview` call as `{"error": ..., "data": ...}`.
+fn call_update_callback(callback: &PyObject, error: Option<String>, data: Value) {
+    Python::with_gil(|py| {
+        let args: PyObject = json_to_py(py, &json!({"error": error, "data": data}));
+        if let Err(e) = callback.call1(py, (args,)) {
+            e.print(py);
+        }
+    });
+}
+
 impl WindowManager {
-    #[new]
-    #[pyo3(text_signature = "(command, listener,config_path, assets_dir)")]
-    fn py_new(
+    fn new(
         command: Py<PyFunction>,
         listener: Py<PyFunction>,
         on_start: Py<PyFunction>,
         on_stop: Py<PyFunction>,
-        config: PyObject,
-        assets_dir: String,
+        config: Config,
+        assets_dir: Option<String>,
+        mode: Option<&str>,
     ) -> PyResult<Self> {
+        let mode = RunMode::select(mode, config.build.mode).map_err(PyValueError::new_err)?;
+        // In dev mode `devPath` may name a directory to serve instead of a dev server url.
+        let dev_dir = Some(config.build.dev_path.clone())
+            .filter(|path| mode == RunMode::Dev && !path.is_empty() && !path.contains("://"));
+        let build_path = match (dev_dir.or(assets_dir).or_else(|| config.build.build_path.clone()), mode) {
+            (Some(build_path), _) => PathBuf::from(build_path),
+            // The dev server serves the frontend, so a build is optional.
+            (None, RunMode::Dev) => PathBuf::new(),
+            (None, RunMode::Prod) => {
+                return Err(PyValueError::new_err("Production mode needs build.buildPath or an assets_dir"));
+            }
+        };
+        let mut protocols = Protocols::new(
+            &config.protocols.clone().unwrap_or_default(),
+            &config.assets.clone().unwrap_or_default(),
+            &build_path,
+        )?;
+        if mode == RunMode::Dev && config.build.dev_path.contains("://") {
+            protocols.set_dev_url(&config.build.dev_path);
+        }
+        let security = SecurityHeaders::new(&config.security.clone().unwrap_or_default())
+            .map_err(|e| PyValueError::new_err(format!("Invalid security config: {}", e)))?;
+        protocols.set_dynamic_html(security.uses_nonce());
         Ok(Self {
             webviews: Arc::new(Mutex::new(HashMap::new())),
             command: Arc::new(Mutex::new(command)),
             listener: Arc::new(Mutex::new(listener)),
             on_start: Arc::new(Mutex::new(on_start)),
             on_stop: Arc::new(Mutex::new(on_stop)),
-            config: Arc::new(Mutex::new(load_py_config(config).unwrap())),
-            proxy: None,
-            base_path: PathBuf::from(assets_dir),
-            webview_windows: Arc::new(Mutex::new(HashMap::new())),
-            event_loop: EventLoopBuilder::<UserEvent>::with_user_event().build(),
+            config: Arc::new(Mutex::new(config)),
+            proxy: Arc::new(Mutex::new(None)),
+            protocols: Arc::new(protocols),
+            protocol_handlers: Arc::new(Mutex::new(vec![])),
+            security: Arc::new(security),
+            mode,
+            running: AtomicBool::new(false),
+            id: NEXT_MANAGER_ID.fetch_add(1, Ordering::Relaxed),
         })
     }
 
-    #[pyo3(text_signature = "(self, event, data)")]
-    fn emit(&self, event: String, data: PyObject) {
-        if let Some(proxy) = self.proxy.clone() {
-            Python::with_gil(|py| {
-                proxy.lock().unwrap().send_event(UserEvent::Emit(EmitData {
-                    event,
-                    data: Box::new(py_to_json(py, data)),
-                })).unwrap();
-            })
+    /// Whether this thread holds the event loop created for this manager.
+    fn owns_event_loop(&self) -> bool {
+        EVENT_LOOP.with(|cell| cell.borrow().as_ref().is_some_and(|(id, _)| *id == self.id))
+    }
+
+    /// Before `run()` windows only exist in the config, afterwards in the label map.
+    fn has_window(&self, label: &str) -> bool {
+        if !self.running.load(Ordering::SeqCst) {
+            self.config.lock().unwrap().pywui.windows.iter()
+                .any(|w| w.label.as_deref() == Some(label))
+        } else {
+            self.webviews.lock().unwrap().contains_key(label)
         }
-        // webview.evaluate_script().unwrap()
     }
+
+    /// Accepts a single label or a list of labels, all of which must name an existing window.
+    fn extract_labels(&self, py: Python, labels: PyObject) -> PyResult<Vec<String>> {
+        let labels = match labels.extract::<String>(py) {
+            Ok(label) => vec![label],
+            Err(_) => labels.extract::<Vec<String>>(py)
+                .map_err(|_| PyTypeError::new_err("Expected a window label or a list of window labels"))?,
+        };
+        if let Some(unknown) = labels.iter().find(|label| !self.has_window(label)) {
+            return Err(PyValueError::new_err(format!("No window with label '{}'", unknown)));
+        }
+        Ok(labels)
+    }
+
+    /// Spawns `beforeDevCommand`, if any, and blocks until `devPath` answers.
+    fn start_dev_server(&self, py: Python, config: &Config) -> PyResult<Option<DevServer>> {
+        let command = config.build.before_dev_command.trim();
+        let mut server = match command.is_empty() {
+            true => None,
+            false => Some(DevServer::spawn(command)
+                .map_err(|e| PyRuntimeError::new_err(format!("Failed to run beforeDevCommand: {}", e)))?),
+        };
+        let dev_path = config.build.dev_path.clone();
+        if dev_path.starts_with("http://") || dev_path.starts_with("https://") {
+            let timeout = config.build.dev_server_timeout.map(Duration::from_secs).unwrap_or(DEFAULT_DEV_SERVER_TIMEOUT);
+            // A failure drops `server`, which kills the command.
+            py.allow_threads(|| wait_for(&dev_path, timeout, server.as_mut()))
+                .map_err(PyRuntimeError::new_err)?;
+        }
+        Ok(server)
+    }
+
+    /// Starts a live reload watcher per served directory; they stop when dropped.
+    fn watch_assets(&self, proxy: &EventLoopProxy<UserEvent>) -> Vec<notify::RecommendedWatcher> {
+        let mut watchers = vec![];
+        for (root, origins) in self.protocols.watch_targets() {
+            let proxy = proxy.clone();
+            let watched = live_reload::watch(&root, origins, move |change| {
+                let _ = proxy.send_event(UserEvent::AssetsChanged(change));
+            });
+            match watched {
+                Ok(watcher) => watchers.push(watcher),
+                Err(e) => eprintln!("Live reload disabled for {}: {}", root.display(), e),
+            }
+        }
+        watchers
+    }
+
+    fn send_event(&self, event: UserEvent) -> PyResult<()> {
+        match self.proxy.lock().unwrap().as_ref() {
+            Some(proxy) => proxy.send_event(event)
+                .map_err(|_| PyRuntimeError::new_err("The event loop is closed")),
+            None => Err(PyRuntimeError::new_err("The window manager has no event loop")),
+        }
+    }
+}
+
+#[pymethods]
+impl WindowManager {
+    #[new]
+    #[pyo3(signature = (command, listener, on_start, on_stop, config, assets_dir=None, mode=None))]
+    fn py_new(
+        command: Py<PyFunction>,
+        listener: Py<PyFunction>,
+        on_start: Py<PyFunction>,
+        on_stop: Py<PyFunction>,
+        config: PyObject,
+        assets_dir: Option<String>,
+        mode: Option<String>,
+    ) -> PyResult<Self> {
+        let config = load_py_config(config).map_err(config_error)?;
+        if EVENT_LOOP.with(|cell| cell.borrow().is_some()) {
+            return Err(PyRuntimeError::new_err(
+                "Another window manager on this thread has not run yet, only one can own the event loop",
+            ));
+        }
+        let manager = Self::new(command, listener, on_start, on_stop, config, assets_dir, mode.as_deref())?;
+        let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+        *manager.proxy.lock().unwrap() = Some(event_loop.create_proxy());
+        EVENT_LOOP.with(|cell| cell.replace(Some((manager.id, event_loop))));
+        Ok(manager)
+    }
+
+    #[pyo3(signature = (event, data, exclude=None))]
+    fn emit(&self, py: Python, event: String, data: PyObject, exclude: Option<PyObject>) -> PyResult<()> {
+        let target = match exclude {
+            Some(exclude) => EmitTarget::Except(self.extract_labels(py, exclude)?),
+            None => EmitTarget::All,
+        };
+        self.send_event(UserEvent::Emit(EmitData {
+            event,
+            data: Box::new(py_to_json(py, data)?),
+            target,
+        }))
+    }
+
+    #[pyo3(text_signature = "(self, target, event, data)")]
+    fn emit_to(&self, py: Python, target: PyObject, event: String, data: PyObject) -> PyResult<()> {
+        let labels = self.extract_labels(py, target)?;
+        self.send_event(UserEvent::Emit(EmitData {
+            event,
+            data: Box::new(py_to_json(py, data)?),
+            target: EmitTarget::Only(labels),
+        }))
+    }
+
+    /// `"dev"` or `"prod"`.
+    #[getter]
+    fn mode(&self) -> &'static str {
+        self.mode.as_str()
+    }
+
     #[pyo3(text_signature = "(self,label= None)")]
-    fn close_window(&self, label: Option<String>) {
-        if let Some(proxy) = self.proxy.clone() {
-            if let Some(lbl) = label {
-                if let Some(window_id) = self.webviews.clone().lock().unwrap().get(&lbl) {
-                    proxy.lock().unwrap().send_event(UserEvent::Close(*window_id)).unwrap();
-                }
-            } else {
-                proxy.lock().unwrap().send_event(UserEvent::Exit()).unwrap();
+    fn close_window(&self, label: Option<String>) -> PyResult<()> {
+        if let Some(lbl) = label {
+            let window_id = self.webviews.lock().unwrap().get(&lbl).cloned();
+            if let Some(window_id) = window_id {
+                self.send_event(UserEvent::Close(window_id))?;
             }
+            Ok(())
+        } else {
+            self.send_event(UserEvent::Exit())
         }
-        // webview.evaluate_script().unwrap()
     }
 
     #[pyo3(text_signature = "(self)")]
@@ -262,183 +483,196 @@ impl WindowManager {
 
     #[pyo3(text_signature = "(self, label, updates)")]
     fn update_window(&self, py: Python, label: &str, updates: PyObject) -> PyResult<()> {
-        println!("Here :::");
-        // if let Some(proxy) = self.proxy.clone() {
-        // let updates_json: Value = py_to_json(py, updates);
-        // // proxy.lock().unwrap().send_event(UserEvent::UpdateWindow(label.to_string(), updates_json, false)).unwrap();
-        // if let Some((_id, (window, webview, _label))) = find_by_label(&self.webview_windows.lock().unwrap(), label) {
-        //     // Update other window properties
-        //     if let Some(width) = updates_json.get("width").and_then(|v| v.as_i64()) {
-        //         if let Some(height) = updates_json.get("height").and_then(|v| v.as_i64()) {
-        //             window.set_inner_size(LogicalSize {
-        //                 width: width as u32,
-        //                 height: height as u32,
-        //             });
-        //         }
-        //     }
-        //     if let Some(resizable) = updates_json.get("resizable").and_then(|v| v.as_bool()) {
-        //         window.set_resizable(resizable);
-        //     }
-        //     if let Some(minimizable) = updates_json.get("minimizable").and_then(|v| v.as_bool()) {
-        //         window.set_minimizable(minimizable);
-        //     }
-        //     if let Some(maximizable) = updates_json.get("maximizable").and_then(|v| v.as_bool()) {
-        //         window.set_maximizable(maximizable);
-        //     }
-        //     if let Some(closable) = updates_json.get("closable").and_then(|v| v.as_bool()) {
-        //         window.set_closable(closable);
-        //     }
-        //     if let Some(fullscreen) = updates_json.get("fullscreen").and_then(|v| v.as_bool()) {
-        //         window.set_fullscreen(if fullscreen {
-        //             Some(tao::window::Fullscreen::Borderless(None))
-        //         } else {
-        //             None
-        //         });
-        //     }
-        //     if let Some(visible) = updates_json.get("visible").and_then(|v| v.as_bool()) {
-        //         window.set_visible(visible);
-        //     }
-        //     if let Some(always_on_top) = updates_json.get("always_on_top").and_then(|v| v.as_bool()) {
-        //         window.set_always_on_top(always_on_top);
-        //     }
-        //     if let Some(title) = updates_json.get("title").and_then(|v| v.as_str()) {
-        //         window.set_title(title);
-        //     }
-        //     if let Some(background_color) = updates_json.get("background_color").and_then(|v| {
-        //         v.as_array().and_then(|arr| {
-        //             if arr.len() == 4 {
-        //                 Some((
-        //                     arr[0].as_u64()? as u8,
-        //                     arr[1].as_u64()? as u8,
-        //                     arr[2].as_u64()? as u8,
-        //                     arr[3].as_u64()? as u8,
-        //                 ))
-        //             } else {
-        //                 None
-        //             }
-        //         })
-        //     }) {
-        //         window.set_background_color(Some(background_color));
-        //     }
-        // }
-        Ok(())
-        // };
+        if !self.has_window(label) {
+            return Err(PyValueError::new_err(format!("No window with label '{}'", label)));
+        }
+        let update = WindowUpdate::from_value(py_to_json(py, updates)?)
+            .map_err(|e| PyValueError::new_err(format!("Invalid update for window '{}': {}", label, e)))?;
+        self.send_event(UserEvent::UpdateWindow(label.to_string(), update))
     }
 
-    #[pyo3(text_signature = "(self, label, updates)")]
-    fn update_webview(&self, py: Python, label: &str, updates: PyObject) -> PyResult<()> {
-        // if let Some(proxy) = self.proxy.clone() {
-        let updates_json: Value = py_to_json(py, updates);
-        // proxy.lock().unwrap().send_event(UserEvent::UpdateWindow(label.to_string(), updates_json, true)).unwrap();
-        if let Some((_id, (window, webview, _label))) = find_by_label(&self.webview_windows.lock().unwrap(), label) {
-            if let Some(url) = updates_json.get("url").and_then(|v| v.as_str()) {
-                webview.load_url(url).unwrap();
-            }
-            if let Some(focus) = updates_json.get("focus").and_then(|v| v.as_bool()) {
-                if focus {
-                    webview.focus().expect("Error whe focusing webview")
-                }
-            }
-            if let Some(script) = updates_json.get("script").and_then(|v| v.as_str()) {
-                webview.evaluate_script(script).unwrap();
-            }
-            if let Some(visible) = updates_json.get("visible").and_then(|v| v.as_bool()) {
-                webview.set_visible(visible).unwrap();
-            }
-            if let Some(html) = updates_json.get("html").and_then(|v| v.as_str()) {
-                webview.load_html(html).unwrap();
-            }
-            if let Some(devtools) = updates_json.get("devtools").and_then(|v| v.as_bool()) {
-                if devtools {
-                    webview.close_devtools();
-                } else {
-                    webview.open_devtools();
-                }
-            }
-            if let Some(clear) = updates_json.get("clear").and_then(|v| v.as_bool()) {
-                if (clear) {
-                    webview.clear_all_browsing_data().expect("Error when erased browser data");
-                }
-            }
+    #[pyo3(signature = (label, updates, callback=None))]
+    fn update_webview(&self, py: Python, label: &str, updates: PyObject, callback: Option<PyObject>) -> PyResult<()> {
+        if !self.has_window(label) {
+            return Err(PyValueError::new_err(format!("No window with label '{}'", label)));
         }
-        Ok(())
-        // };
+        let update = WebViewUpdate::from_value(py_to_json(py, updates)?, &self.protocols)
+            .map_err(|e| PyValueError::new_err(format!("Invalid update for webview '{}': {}", label, e)))?;
+        self.send_event(UserEvent::UpdateWebview(label.to_string(), update, callback))
     }
 
 
-    #[pyo3(text_signature = "(self)")]
-    fn run(&mut self) {
-        let base_bath = self.base_path.clone();
+    /// Serves `scheme`, or the paths below `prefix` of the file-serving protocols, from a Python callback.
+    #[pyo3(signature = (handler, scheme=None, prefix=None))]
+    fn register_protocol(&self, handler: PyObject, scheme: Option<String>, prefix: Option<String>) -> PyResult<()> {
+        if self.running.load(Ordering::SeqCst) {
+            return Err(PyRuntimeError::new_err("Protocols must be registered before run()"));
+        }
+        let handler = PyProtocolHandler::new(scheme, prefix, handler)?;
+        self.protocol_handlers.lock().unwrap().push(handler);
+        Ok(())
+    }
 
-        let config = self.config.lock().unwrap();
-        let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+    #[pyo3(text_signature = "(self)")]
+    fn run(&self, py: Python) -> PyResult<()> {
+        if self.running.load(Ordering::SeqCst) {
+            return Err(PyRuntimeError::new_err("The window manager is already running"));
+        }
+        if !self.owns_event_loop() {
+            return Err(PyRuntimeError::new_err("run() must be called on the thread that created the window manager"));
+        }
+        let config = self.config.lock().unwrap().clone();
+        let icon = load_icon(&config).map_err(config_error)?;
+        let mut dev_server = match self.mode {
+            RunMode::Dev => self.start_dev_server(py, &config)?,
+            RunMode::Prod => None,
+        };
+        let event_loop = EVENT_LOOP.with(|cell| cell.borrow_mut().take_if(|(id, _)| *id == self.id))
+            .map(|(_, event_loop)| event_loop)
+            .ok_or_else(|| PyRuntimeError::new_err("The window manager is already running"))?;
         let proxy = event_loop.create_proxy();
+        self.running.store(true, Ordering::SeqCst);
+        let mut watchers = match self.mode == RunMode::Dev && config.build.live_reload.unwrap_or(false) {
+            true => self.watch_assets(&proxy),
+            false => vec![],
+        };
         let command = self.command.clone();
         let listener = self.listener.clone();
-        self.proxy = Some(Arc::new(Mutex::new(proxy.clone())));
         let on_start = self.on_start.clone();
         let on_stop = self.on_stop.clone();
-        let webview_windows = Arc::clone(&self.webview_windows);
-        let mut webview_cloned = self.webviews.clone();
-
-        let protocol_handler: Arc<Mutex<Box<dyn Fn(WebViewId, Request<Vec<u8>>, RequestAsyncResponder) + Send + Sync>>> = Arc::new(Mutex::new(Box::new(move |id, request, responder| {
-            get_wry_response(request, responder, &base_bath)
-        })));
-
-        let handler: Arc<Mutex<Box<dyn Fn(Request<String>)>>> = Arc::new(Mutex::new(Box::new(move |req: Request<String>| {
-            let data: IPCData = serde_json::from_str(req.body()).unwrap();
-            let listeners = listener.lock().unwrap();
-            let commands = command.lock().unwrap();
+        let webview_windows: Arc<Mutex<HashMap<WindowId, (Window, WebView, String)>>> = Arc::new(Mutex::new(HashMap::new()));
+        let webview_cloned = self.webviews.clone();
+
+        let workers = Arc::new(WorkerPool::new("pywui-worker", config.pywui.workers.unwrap_or(4)));
+        let runner = Arc::new(AsyncioRunner::start(py)?);
+        let asset_workers = Arc::new(WorkerPool::new("pywui-assets", 2));
+        let protocol_handlers = Arc::new(self.protocol_handlers.lock().unwrap().clone());
+        let file_schemes = self.protocols.schemes();
+        let mut schemes = file_schemes.clone();
+        for scheme in protocol_handlers.iter().filter_map(|handler| handler.scheme.clone()) {
+            if !schemes.contains(&scheme) {
+                schemes.push(scheme);
+            }
+        }
+        // Slow protocol handlers must not hold up commands, so they get threads of their own.
+        let protocol_workers = Arc::new(WorkerPool::new("pywui-protocols", 2));
+        let protocol_runner = runner.clone();
+        let handler: Arc<Mutex<Box<dyn Fn(WindowId, Request<String>)>>> = Arc::new(Mutex::new(Box::new(move |window_id: WindowId, req: Request<String>| {
+            let data: IPCData = match serde_json::from_str(req.body()) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Rejected malformed IPC message ({}): {}", e, req.body());
+                    return;
+                }
+            };
+            let listener = listener.clone();
+            let command = command.clone();
+            let proxy = proxy.clone();
+            let runner = runner.clone();
+            // Python callbacks run on the worker pool so a slow one never blocks the UI thread.
             match data.event_type.as_str() {
-                "event" => {
+                "event" => workers.execute(move || {
                     Python::with_gil(|py| {
+                        let listener = listener.lock().unwrap().clone_ref(py);
                         let new_args = json!({"event": data.command, "args": data.args});
                         let args: PyObject = json_to_py(py, &new_args);
-                        let py_args = PyTuple::new(py, &[args]).unwrap();
-                        listeners.call1(py, py_args).unwrap();
+                        let result = listener.call1(py, (args,)).and_then(|value| {
+                            if runner.is_awaitable(py, &value) {
+                                runner.submit(py, value, |py, result| {
+                                    if let Err(e) = result {
+                                        e.print(py);
+                                    }
+                                })?;
+                            }
+                            Ok(())
+                        });
+                        if let Err(e) = result {
+                            e.print(py);
+                        }
                     });
-                }
-                "request" => {
+                }),
+                "request" => workers.execute(move || {
                     Python::with_gil(|py| {
+                        let command = command.lock().unwrap().clone_ref(py);
                         let new_args = json!({"command": data.command, "args": data.args});
                         let args: PyObject = json_to_py(py, &new_args);
-                        let py_args = PyTuple::new(py, &[args]).unwrap();
-                        let value = commands.call1(py, py_args).unwrap();
-                        proxy.clone().send_event(UserEvent::Response(ResponseData {
-                            request_id: data.request_id,
-                            data: Box::new(py_to_json(py, value)),
-                        })).unwrap();
+                        match command.call1(py, (args,)) {
+                            Ok(value) if runner.is_awaitable(py, &value) => {
+                                // Coroutine handlers resolve on the asyncio loop and answer from there.
+                                let request_id = data.request_id.clone();
+                                let loop_proxy = proxy.clone();
+                                let submitted = runner.submit(py, value, move |py, result| {
+                                    send_response(&loop_proxy, command_response(py, window_id, request_id, result));
+                                });
+                                if let Err(e) = submitted {
+                                    send_response(&proxy, command_response(py, window_id, data.request_id, Err(e)));
+                                }
+                            }
+                            result => send_response(&proxy, command_response(py, window_id, data.request_id, result)),
+                        }
                     });
-                }
-                _ => {}
+                }),
+                other => eprintln!("Rejected IPC message with unknown event_type '{}'", other),
             }
         })));
 
         for win in config.pywui.windows.iter().clone() {
-            let default_value = if let Some(web_conf) = win.webview.clone() {
+            let mut default_value = if let Some(web_conf) = win.webview.clone() {
                 WebViewAttributes::from(web_conf)
             } else {
                 WebViewAttributes::default()
             };
             // Create a handler closure that captures listeners and commands in Arc<Mutex>
             let cloned_handler = handler.clone();
-            let proto_handler = protocol_handler.clone();
-
-            let custom_protocols: HashMap<String, Box<dyn Fn(WebViewId, Request<Vec<u8>>, RequestAsyncResponder)>> =
-                HashMap::from([(
-                    "pywui".to_string(),
-                    Box::new(move |w: WebViewId, req: Request<Vec<u8>>, res: RequestAsyncResponder| {
-                        let handler_lock = proto_handler.lock().unwrap();
-                        handler_lock(w, req, res)
-                    }) as Box<dyn Fn(WebViewId, Request<Vec<u8>>, RequestAsyncResponder)>
-                )]);
+            // The window id only exists once the window is built, so the ipc handler reads it lazily.
+            let source: Arc<OnceLock<WindowId>> = Arc::new(OnceLock::new());
+            let ipc_source = source.clone();
+
+            let mut custom_protocols: HashMap<String, Box<dyn Fn(WebViewId, Request<Vec<u8>>, RequestAsyncResponder)>> = HashMap::new();
+            for scheme in schemes.iter() {
+                let protocols = self.protocols.clone();
+                let asset_workers = asset_workers.clone();
+                let protocol_handlers = protocol_handlers.clone();
+                let workers = protocol_workers.clone();
+                let runner = protocol_runner.clone();
+                let security = self.security.clone();
+                let serves_files = file_schemes.contains(scheme);
+                let name = scheme.clone();
+                custom_protocols.insert(scheme.clone(), Box::new(move |_: WebViewId, req: Request<Vec<u8>>, res: RequestAsyncResponder| {
+                    // Disk reads and Python handlers run off the UI thread; the responder completes the request from there.
+                    let path = req.uri().path().to_string();
+                    let security = security.clone();
+                    if let Some(handler) = find_handler(&protocol_handlers, &name, serves_files, &path) {
+                        let callback = handler.callback.clone();
+                        let runner = runner.clone();
+                        workers.execute(move || custom_protocol::respond(&callback, req, res, &runner, security));
+                    } else if serves_files {
+                        let protocols = protocols.clone();
+                        let name = name.clone();
+                        asset_workers.execute(move || {
+                            let mut response = protocols.handle(&name, &req);
+                            security.apply(&path, &mut response);
+                            res.respond(response);
+                        });
+                    } else {
+                        let mut response = http::Response::builder().status(404).body(Vec::new()).unwrap();
+                        security.apply(&path, &mut response);
+                        res.respond(response);
+                    }
+                }));
+            }
 
+            // The bridge goes first so user scripts from the config can already use `window.__pywui__`.
+            let mut initialization_scripts = vec![(get_init_script(self.mode.as_str()), true)];
+            initialization_scripts.append(&mut default_value.initialization_scripts);
             let web_view = WebViewAttributes {
-                url: Option::from(default_value.url.unwrap_or(config.clone().build.dev_path)),
-                initialization_scripts: vec![],
+                url: Some(self.protocols.resolve_url(&default_value.url.unwrap_or_default())),
+                initialization_scripts,
                 ipc_handler: Some(Box::new(move |req: Request<String>| {
-                    let handler_lock = cloned_handler.lock().unwrap();
-                    handler_lock(req)
+                    if let Some(window_id) = ipc_source.get() {
+                        let handler_lock = cloned_handler.lock().unwrap();
+                        handler_lock(*window_id, req)
+                    }
                 })),
                 custom_protocols,
                 ..default_value
@@ -447,118 +681,265 @@ impl WindowManager {
                 web_view,
                 win.clone(),
                 &event_loop,
-                config.clone().icon.get_for_current_os(),
+                icon.clone(),
             );
             let window_id = new_window.0.id();
+            let _ = source.set(window_id);
             let label = win.label.clone().unwrap_or_else(|| {
                 format!(
                     "Window {}",
                     webview_cloned.lock().unwrap().len() + 1
                 )
             });
-            self.webview_windows.lock().unwrap().insert(window_id.clone(), (new_window.0, new_window.1, label.clone()));
+            webview_windows.lock().unwrap().insert(window_id.clone(), (new_window.0, new_window.1, label.clone()));
             webview_cloned.lock().unwrap().insert(label, window_id.clone());
         }
 
-        event_loop.run(move |event, _, control_flow| {
-            *control_flow = ControlFlow::Wait;
-            match event {
-                Event::NewEvents(StartCause::Init) => {
-                    Python::with_gil(|py| {
-                        let args: PyObject = json_to_py(py, &json!({"label": "main"}));
-                        let py_args = PyTuple::new(py, &[args]).unwrap();
-                        on_start.lock().unwrap().call1(py, py_args).unwrap()
-                    });
-                }
-                Event::UserEvent(UserEvent::Response(data)) => {
-                    for (_, webview) in webview_windows.lock().unwrap().iter().clone() {
-                        let js_code = format!(
-                            r#"
-                                window.dispatchEvent(
-                                    new CustomEvent('{}', {{
-                                        detail: {{data: {} }}
-                                    }})
-                                );
-                            "#,
-                            data.request_id.as_str(), data.data.to_string()
-                        );
-                        webview.1.evaluate_script(js_code.as_str()).unwrap();
-                    }
-                }
-                Event::UserEvent(UserEvent::Emit(data)) => {
-                    for (_, webview) in webview_windows.lock().unwrap().iter().clone() {
-                        let js_code = format!(
-                            r#"
-                                window.dispatchEvent(
-                                    new CustomEvent('{}', {{
-                                        detail: {{ data: {} }}
-                                    }})
-                                );
-                            "#,
-                            data.event.as_str(), data.data.to_string()
-                        );
-                        webview.1.evaluate_script(js_code.as_str()).unwrap();
-                    }
-                }
-                Event::UserEvent(
-                    UserEvent::Close(window_id)
-                ) => {
-                    let mut wm = webview_windows.lock().unwrap();
-                    if let Some(w) = wm.remove(&window_id) {
-                        let label = w.2.clone();
+        // Release the GIL for the lifetime of the event loop so worker threads can call into Python.
+        let event_loop = AssertSend(event_loop);
+        let webview_windows = AssertSend(webview_windows);
+        py.allow_threads(move || {
+            let event_loop = event_loop.into_inner();
+            let webview_windows = webview_windows.into_inner();
+            event_loop.run(move |event, _, control_flow| {
+                *control_flow = ControlFlow::Wait;
+                match event {
+                    Event::NewEvents(StartCause::Init) => {
                         Python::with_gil(|py| {
-                            let args: PyObject = json_to_py(py, &json!({"label": label}));
+                            let args: PyObject = json_to_py(py, &json!({"label": "main"}));
                             let py_args = PyTuple::new(py, &[args]).unwrap();
-                            on_stop.lock().unwrap().call1(py, py_args).unwrap()
+                            on_start.lock().unwrap().call1(py, py_args).unwrap()
                         });
                     }
-                    if wm.len() == 0 {
-                        println!("Pywui exit ....");
-                        *control_flow = ControlFlow::Exit
+                    Event::UserEvent(UserEvent::Response(data)) => {
+                        // Only the webview that sent the request is waiting for its answer.
+                        if let Some((_, webview, label)) = webview_windows.lock().unwrap().get(&data.window_id) {
+                            let js_code = dispatch_event_script(&data.request_id, &json!({"data": data.data, "error": data.error}));
+                            if let Err(e) = webview.evaluate_script(&js_code) {
+                                eprintln!("Failed to send response to webview '{}': {}", label, e);
+                            }
+                        }
                     }
-                }
-                Event::UserEvent(
-                    UserEvent::Exit()
-                ) => {
-                    println!("Pywui exit ....");
-                    Python::with_gil(|py| {
-                        let args: PyObject = json_to_py(py, &json!({"label": "main"}));
-                        let py_args = PyTuple::new(py, &[args]).unwrap();
-                        on_stop.lock().unwrap().call1(py, py_args).unwrap()
-                    });
-                    *control_flow = ControlFlow::Exit
-                }
-                Event::WindowEvent {
-                    window_id,
-                    event: WindowEvent::CloseRequested,
-                    ..
-                } => {
-                    let mut wwin = webview_windows.lock().unwrap();
-                    if let Some(ww) = wwin.remove(&window_id) {
-                        let label = ww.2.clone();
+                    Event::UserEvent(UserEvent::Emit(data)) => {
+                        let js_code = dispatch_event_script(&data.event, &json!({"data": data.data}));
+                        for (_, (_, webview, label)) in webview_windows.lock().unwrap().iter() {
+                            if !data.target.includes(label) {
+                                continue;
+                            }
+                            if let Err(e) = webview.evaluate_script(js_code.as_str()) {
+                                eprintln!("Failed to emit '{}' to webview '{}': {}", data.event, label, e);
+                            }
+                        }
+                    }
+                    Event::UserEvent(UserEvent::UpdateWebview(label, update, callback)) => {
+                        let wm = webview_windows.lock().unwrap();
+                        let Some((_, (_, webview, _))) = find_by_label(&wm, &label) else {
+                            if let Some(callback) = callback {
+                                call_update_callback(&callback, Some(format!("No window with label '{}'", label)), Value::Null);
+                            }
+                            return;
+                        };
+                        if let Err(e) = update.apply(webview) {
+                            eprintln!("Failed to update webview '{}': {}", label, e);
+                            if let Some(callback) = callback {
+                                call_update_callback(&callback, Some(e.to_string()), Value::Null);
+                            }
+                            return;
+                        }
+                        match (update.script, callback) {
+                            (Some(script), Some(callback)) => {
+                                let result = webview.evaluate_script_with_callback(&wrap_script(&script), move |result| {
+                                    let result: Value = serde_json::from_str(&result).unwrap_or(Value::Null);
+                                    let error = result.get("error").and_then(|e| e.as_str()).map(str::to_string);
+                                    let data = result.get("data").cloned().unwrap_or(Value::Null);
+                                    call_update_callback(&callback, error, data);
+                                });
+                                if let Err(e) = result {
+                                    eprintln!("Failed to evaluate script in webview '{}': {}", label, e);
+                                }
+                            }
+                            (Some(script), None) => {
+                                if let Err(e) = webview.evaluate_script(&script) {
+                                    eprintln!("Failed to evaluate script in webview '{}': {}", label, e);
+                                }
+                            }
+                            (None, Some(callback)) => call_update_callback(&callback, None, Value::Null),
+                            (None, None) => {}
+                        }
+                    }
+                    Event::UserEvent(UserEvent::UpdateWindow(label, update)) => {
+                        let wm = webview_windows.lock().unwrap();
+                        if let Some((_, (window, _, _))) = find_by_label(&wm, &label) {
+                            update.apply(window);
+                        }
+                    }
+                    Event::UserEvent(
+                        UserEvent::Close(window_id)
+                    ) => {
+                        let mut wm = webview_windows.lock().unwrap();
+                        if let Some(w) = wm.remove(&window_id) {
+                            let label = w.2.clone();
+                            webview_cloned.lock().unwrap().remove(&label);
+                            Python::with_gil(|py| {
+                                let args: PyObject = json_to_py(py, &json!({"label": label}));
+                                let py_args = PyTuple::new(py, &[args]).unwrap();
+                                on_stop.lock().unwrap().call1(py, py_args).unwrap()
+                            });
+                        }
+                        if wm.len() == 0 {
+                            println!("Pywui exit ....");
+                            *control_flow = ControlFlow::Exit
+                        }
+                    }
+                    Event::UserEvent(
+                        UserEvent::Exit()
+                    ) => {
+                        println!("Pywui exit ....");
                         Python::with_gil(|py| {
-                            let args: PyObject = json_to_py(py, &json!({"label": label}));
+                            let args: PyObject = json_to_py(py, &json!({"label": "main"}));
                             let py_args = PyTuple::new(py, &[args]).unwrap();
                             on_stop.lock().unwrap().call1(py, py_args).unwrap()
                         });
-                        if wwin.len() == 0 {
-                            *control_flow = ControlFlow::Exit
-                        } else if (ww.2.eq("main")) {
-                            println!("Main exit");
-                            *control_flow = ControlFlow::Exit;
+                        *control_flow = ControlFlow::Exit
+                    }
+                    Event::WindowEvent {
+                        window_id,
+                        event: WindowEvent::CloseRequested,
+                        ..
+                    } => {
+                        let mut wwin = webview_windows.lock().unwrap();
+                        if let Some(ww) = wwin.remove(&window_id) {
+                            let label = ww.2.clone();
+                            webview_cloned.lock().unwrap().remove(&label);
+                            Python::with_gil(|py| {
+                                let args: PyObject = json_to_py(py, &json!({"label": label}));
+                                let py_args = PyTuple::new(py, &[args]).unwrap();
+                                on_stop.lock().unwrap().call1(py, py_args).unwrap()
+                            });
+                            if wwin.len() == 0 {
+                                *control_flow = ControlFlow::Exit
+                            } else if (ww.2.eq("main")) {
+                                println!("Main exit");
+                                *control_flow = ControlFlow::Exit;
+                            }
+                        }
+                    }
+                    Event::UserEvent(UserEvent::AssetsChanged(change)) => {
+                        println!("Assets changed: {}", change.paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", "));
+                        for (_, (_, webview, label)) in webview_windows.lock().unwrap().iter() {
+                            if !webview.url().is_ok_and(|url| change.affects(&url)) {
+                                continue;
+                            }
+                            if let Err(e) = webview.evaluate_script(change.script()) {
+                                eprintln!("Failed to reload webview '{}': {}", label, e);
+                            }
+                        }
+                    }
+                    Event::LoopDestroyed => {
+                        watchers.clear();
+                        if let Some(server) = dev_server.as_mut() {
+                            server.kill();
                         }
                     }
+                    _ => {}
                 }
-                _ => {}
-            }
-        });
+            })
+        })
     }
 }
 
+/// Packs the frontend build in `src` into a single asset bundle at `out`, returning the number of files.
+#[pyfunction]
+#[pyo3(text_signature = "(src, out)")]
+fn pack_assets(py: Python, src: PathBuf, out: PathBuf) -> PyResult<usize> {
+    Ok(py.allow_threads(|| bundle::pack_dir(&src, &out))?)
+}
+
+/// Reads `pywui.conf.json`, `.json5` or `.toml` at `path`, or the closest one up from the directory
+/// `path` or the working directory, with relative paths resolved against the file.
+///
+/// `strict` rejects keys the config does not know instead of ignoring them.
+#[pyfunction]
+#[pyo3(signature = (path=None, strict=false))]
+fn load_config(py: Python, path: Option<PathBuf>, strict: bool) -> PyResult<PyObject> {
+    let (_, value) = config::load_config_value(path.as_deref(), strict).map_err(config_error)?;
+    Ok(json_to_py(py, &value))
+}
+
+/// The JSON Schema of the config file, for editors and validators.
+#[pyfunction]
+fn config_schema(py: Python) -> PyObject {
+    json_to_py(py, &schema::config_schema())
+}
+
 /// A Python module implemented in Rust.
 #[pymodule]
 #[pyo3(name = "pywui_rs")]
 fn pywui_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
     let _ = m.add_class::<WindowManager>();
+    m.add("PROTOCOL_VERSION", PROTOCOL_VERSION)?;
+    m.add("ConfigError", m.py().get_type::<ConfigError>())?;
+    m.add_function(wrap_pyfunction!(pack_assets, m)?)?;
+    m.add_function(wrap_pyfunction!(load_config, m)?)?;
+    m.add_function(wrap_pyfunction!(config_schema, m)?)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use pyo3::types::PyDict;
+
+    use super::*;
+
+    fn callback(py: Python) -> Py<PyFunction> {
+        py.eval(c"lambda info=None: None", None, None).unwrap().downcast_into::<PyFunction>().unwrap().unbind()
+    }
+
+    #[test]
+    fn manager_methods_can_be_called_from_worker_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<WindowManager>();
+
+        pyo3::prepare_freethreaded_python();
+        let manager = Python::with_gil(|py| {
+            let config = Config::from_value(json!({"pywui": {"windows": [{"label": "main"}]}})).unwrap();
+            let callbacks = [callback(py), callback(py), callback(py), callback(py)];
+            let [command, listener, on_start, on_stop] = callbacks;
+            let manager = WindowManager::new(command, listener, on_start, on_stop, config, None, Some("dev")).unwrap();
+            Py::new(py, manager).unwrap()
+        });
+        let workers = WorkerPool::new("pywui-test", 1);
+        let (sender, receiver) = channel();
+        workers.execute(move || {
+            let outcome = Python::with_gil(|py| -> PyResult<_> {
+                let manager = manager.bind(py);
+                let mode: String = manager.getattr("mode")?.extract()?;
+                let missing = manager.call_method1("update_window", ("missing", PyDict::new(py))).unwrap_err();
+                let detached = manager.call_method1("update_window", ("main", PyDict::new(py))).unwrap_err();
+                Ok((mode, missing.to_string(), detached.to_string()))
+            });
+            sender.send(outcome.unwrap()).unwrap();
+        });
+        let (mode, missing, detached) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(mode, "dev");
+        assert_eq!(missing, "ValueError: No window with label 'missing'");
+        assert_eq!(detached, "RuntimeError: The window manager has no event loop");
+    }
+
+    #[test]
+    fn loads_the_configured_icon() {
+        let icon = |path: &str| {
+            let config = json!({"icon": {"linux": path, "macos": path, "windows": path}});
+            load_icon(&Config::from_value(config).unwrap())
+        };
+        assert!(load_icon(&Config::from_value(json!({})).unwrap()).unwrap().is_none());
+        assert!(icon(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/icons/pywui.png")).unwrap().is_some());
+        let error = icon("missing.png").unwrap_err();
+        assert_eq!(error.path, format!("icon.{}", OS));
+        assert!(error.message.starts_with("cannot read 'missing.png'"));
+        let error = icon(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")).unwrap_err();
+        assert!(error.message.starts_with("cannot decode"));
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Quiet period after the last file event before webviews are told, so a build writing
+/// dozens of files triggers a single reload.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Refreshes every stylesheet in place by busting its url.
+pub const CSS_RELOAD_SCRIPT: &str = r#"document.querySelectorAll('link[rel="stylesheet"]').forEach(function (link) {
+    var url = new URL(link.href);
+    url.searchParams.set("pywui-reload", Date.now());
+    link.href = url.toString();
+});"#;
+pub const RELOAD_SCRIPT: &str = "location.reload();";
+
+/// A debounced batch of changes below one asset root.
+#[derive(Debug)]
+pub struct AssetChange {
+    /// Url prefixes of the pages served from the root that changed.
+    pub origins: Vec<String>,
+    /// Files created, modified or removed in this batch, without duplicates.
+    pub paths: Vec<PathBuf>,
+    /// Stylesheets can be swapped without reloading the page.
+    pub css_only: bool,
+}
+
+impl AssetChange {
+    pub fn affects(&self, url: &str) -> bool {
+        self.origins.iter().any(|origin| url.starts_with(origin))
+    }
+
+    pub fn script(&self) -> &'static str {
+        match self.css_only {
+            true => CSS_RELOAD_SCRIPT,
+            false => RELOAD_SCRIPT,
+        }
+    }
+}
+
+/// Watches `root` recursively and calls `on_change` once its files settle.
+///
+/// The returned watcher stops watching when dropped.
+pub fn watch<F>(root: &Path, origins: Vec<String>, on_change: F) -> notify::Result<RecommendedWatcher>
+where
+    F: Fn(AssetChange) + Send + 'static,
+{
+    let (sender, receiver) = channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(root, RecursiveMode::Recursive)?;
+    println!("Watching {} for changes", root.display());
+    thread::Builder::new()
+        .name("pywui-live-reload".to_string())
+        .spawn(move || {
+            // Ends when the watcher, and with it the sender, is dropped.
+            while let Ok(first) = receiver.recv() {
+                let mut changed = changed_paths(first);
+                loop {
+                    match receiver.recv_timeout(DEBOUNCE) {
+                        Ok(event) => changed.extend(changed_paths(event)),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                changed.sort();
+                changed.dedup();
+                if changed.is_empty() {
+                    continue;
+                }
+                on_change(AssetChange {
+                    origins: origins.clone(),
+                    css_only: is_css_only(&changed),
+                    paths: changed,
+                });
+            }
+        })
+        .expect("Failed to spawn live reload thread");
+    Ok(watcher)
+}
+
+fn changed_paths(event: notify::Result<Event>) -> Vec<PathBuf> {
+    match event {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => event.paths,
+        Ok(_) => vec![],
+        Err(e) => {
+            eprintln!("Live reload watcher error: {}", e);
+            vec![]
+        }
+    }
+}
+
+fn is_css_only(paths: &[PathBuf]) -> bool {
+    paths.iter().all(|path| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("css"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn swaps_stylesheets_only_when_every_change_is_css() {
+        assert!(is_css_only(&[PathBuf::from("a/app.css"), PathBuf::from("theme.CSS")]));
+        assert!(!is_css_only(&[PathBuf::from("app.css"), PathBuf::from("app.js")]));
+        let change = AssetChange {
+            origins: vec!["pywui://".to_string()],
+            paths: vec![PathBuf::from("app.js")],
+            css_only: false,
+        };
+        assert!(change.affects("pywui://pywui/index.html"));
+        assert!(!change.affects("https://example.com/"));
+        assert_eq!(change.script(), RELOAD_SCRIPT);
+    }
+
+    #[test]
+    fn reports_debounced_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sender, receiver) = channel();
+        let _watcher = watch(dir.path(), vec![], move |change| sender.send(change).unwrap()).unwrap();
+        fs::write(dir.path().join("app.css"), "body {}").unwrap();
+
+        let change = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(change.css_only);
+        assert!(change.paths.iter().any(|path| path.ends_with("app.css")), "{:?}", change.paths);
+    }
+}
@@ -0,0 +1,972 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use http::header::{
+    ACCEPT_ENCODING, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE,
+    LAST_MODIFIED, RANGE, VARY,
+};
+use http::{HeaderValue, Response};
+use percent_encoding::percent_decode_str;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use wry::http::Request;
+
+use crate::bundle::Bundle;
+use crate::cache::{is_not_modified, AssetCache, CacheControlRule, MemoryCacheConfig};
+use crate::encoding::{accepted_encodings, encoded_entity_tag, gzip, CompressionConfig, Encoding};
+use crate::range::{entity_tag, if_range_matches, parse_range, RangeRequest, MAX_RANGE_CHUNK};
+use crate::util::matches_pattern;
+
+const DEFAULT_SCHEME: &str = "pywui";
+const STANDARD_SCHEMES: [&str; 6] = ["http", "https", "ftp", "file", "ws", "wss"];
+/// Assets may change on disk between runs, so by default clients revalidate every time.
+const DEFAULT_CACHE_CONTROL: &str = "no-cache";
+const DEFAULT_INDEX: &str = "index.html";
+
+/// Corrections to `mime_guess` for extensions common in frontend builds.
+const BUILTIN_MIME_TYPES: [(&str, &str); 4] = [
+    ("mjs", "text/javascript"),
+    ("map", "application/json"),
+    ("woff", "font/woff"),
+    ("otf", "font/otf"),
+];
+
+/// A custom scheme, optionally limited to one host, that serves files from `root`.
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+pub struct ProtocolConfig {
+    pub scheme: String,
+    pub host: Option<String>,
+    /// Directory or asset bundle, defaults to the build path.
+    pub root: Option<String>,
+    pub symlinks: Option<SymlinkPolicy>,
+}
+
+/// Settings shared by every file-serving protocol.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct AssetsConfig {
+    /// Extension (without the dot) to content type, taking precedence over the built-in table.
+    #[serde(rename = "mimeTypes")]
+    pub mime_types: Option<HashMap<String, String>>,
+    /// First matching rule wins; unmatched paths get `no-cache`.
+    #[serde(rename = "cacheControl")]
+    pub cache_control: Option<Vec<CacheControlRule>>,
+    /// Keeps hot files in memory when set.
+    pub cache: Option<MemoryCacheConfig>,
+    pub compression: Option<CompressionConfig>,
+    /// File served for directory paths, `index.html` by default.
+    pub index: Option<String>,
+    /// Entry document served for unknown paths without an extension, for client-side routers.
+    pub fallback: Option<String>,
+    /// Page served with status 404 when nothing else matches.
+    #[serde(rename = "notFound")]
+    pub not_found: Option<String>,
+}
+
+/// How symlinks below an asset root are followed.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SymlinkPolicy {
+    /// Refuse any path that goes through a symlink.
+    Deny,
+    /// Follow symlinks as long as their target stays inside the root.
+    #[default]
+    WithinRoot,
+    /// Follow symlinks wherever they point.
+    Allow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveError {
+    NotFound,
+    Forbidden,
+}
+
+/// Where a mount's files come from.
+#[derive(Debug, Clone)]
+enum AssetSource {
+    Dir(PathBuf),
+    Bundle(Arc<Bundle>),
+}
+
+impl AssetSource {
+    fn load(path: &Path) -> io::Result<Self> {
+        if !Bundle::is_bundle(path) {
+            return Ok(AssetSource::Dir(path.to_path_buf()));
+        }
+        let bundle = Bundle::open(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("Failed to load asset bundle {}: {}", path.display(), e)))?;
+        Ok(AssetSource::Bundle(Arc::new(bundle)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Mount {
+    scheme: String,
+    host: Option<String>,
+    source: AssetSource,
+    symlinks: SymlinkPolicy,
+}
+
+/// A resolved asset, ready to be read in whole or in part.
+struct Asset<'a> {
+    /// Requested path, which decides the content type even when a precompressed sibling is served.
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+    encoding: Option<Encoding>,
+    body: AssetBody<'a>,
+}
+
+impl Asset<'_> {
+    fn cache_key(&self, encoding: Option<Encoding>) -> PathBuf {
+        match encoding {
+            Some(encoding) => with_extension_suffix(&self.path, encoding.extension()),
+            None => self.path.clone(),
+        }
+    }
+}
+
+enum AssetBody<'a> {
+    File(File),
+    Memory(&'a [u8]),
+}
+
+/// Every asset root the webviews can load from, keyed by scheme and host.
+#[derive(Debug, Clone)]
+pub struct Protocols {
+    mounts: Vec<Mount>,
+    mime_types: HashMap<String, String>,
+    cache_control: Vec<CacheControlRule>,
+    cache: Option<Arc<AssetCache>>,
+    compression: CompressionConfig,
+    index: String,
+    fallback: Option<String>,
+    not_found: Option<String>,
+    dev_url: Option<String>,
+    /// HTML is rewritten per response (to add a CSP nonce), so it is served whole and uncached.
+    dynamic_html: bool,
+}
+
+impl Protocols {
+    /// Fails when a configured root is an asset bundle that cannot be read.
+    pub fn new(configs: &[ProtocolConfig], assets: &AssetsConfig, build_path: &Path) -> io::Result<Self> {
+        let mut mounts: Vec<Mount> = vec![];
+        for config in configs {
+            let root = config.root.as_ref().map(PathBuf::from).unwrap_or_else(|| build_path.to_path_buf());
+            mounts.push(Mount {
+                scheme: config.scheme.clone(),
+                host: config.host.clone(),
+                source: AssetSource::load(&root)?,
+                symlinks: config.symlinks.unwrap_or_default(),
+            });
+        }
+        if mounts.is_empty() {
+            mounts.push(Mount {
+                scheme: DEFAULT_SCHEME.to_string(),
+                host: None,
+                source: AssetSource::load(build_path)?,
+                symlinks: SymlinkPolicy::default(),
+            });
+        }
+        let mut mime_types: HashMap<String, String> = BUILTIN_MIME_TYPES
+            .iter()
+            .map(|(ext, mime)| (ext.to_string(), mime.to_string()))
+            .collect();
+        for (ext, mime) in assets.mime_types.iter().flatten() {
+            mime_types.insert(ext.trim_start_matches('.').to_ascii_lowercase(), mime.clone());
+        }
+        Ok(Self {
+            mounts,
+            mime_types,
+            cache_control: assets.cache_control.clone().unwrap_or_default(),
+            cache: assets.cache.as_ref().map(|config| Arc::new(AssetCache::new(config))),
+            compression: assets.compression.clone().unwrap_or_default(),
+            index: assets.index.clone().unwrap_or_else(|| DEFAULT_INDEX.to_string()),
+            fallback: assets.fallback.clone(),
+            not_found: assets.not_found.clone(),
+            dev_url: None,
+            dynamic_html: false,
+        })
+    }
+
+    /// `Cache-Control` value for a request path.
+    pub fn cache_control(&self, uri_path: &str) -> &str {
+        self.cache_control
+            .iter()
+            .find(|rule| matches_pattern(&rule.pattern, uri_path))
+            .map(|rule| rule.value.as_str())
+            .unwrap_or(DEFAULT_CACHE_CONTROL)
+    }
+
+    /// Content type for `path`, with `charset=utf-8` added to textual types.
+    pub fn content_type(&self, path: &Path) -> String {
+        let ext = path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase);
+        let mime = ext
+            .and_then(|ext| self.mime_types.get(&ext).cloned())
+            .unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream().to_string());
+        if is_textual(&mime) && !mime.contains("charset=") {
+            format!("{}; charset=utf-8", mime)
+        } else {
+            mime
+        }
+    }
+
+    /// Distinct schemes to register on each webview.
+    pub fn schemes(&self) -> Vec<String> {
+        let mut schemes: Vec<String> = vec![];
+        for mount in &self.mounts {
+            if !schemes.contains(&mount.scheme) {
+                schemes.push(mount.scheme.clone());
+            }
+        }
+        schemes
+    }
+
+    /// Resolves relative urls against the dev server instead of the bundled build.
+    pub fn set_dev_url(&mut self, dev_url: &str) {
+        self.dev_url = Some(format!("{}/", dev_url.trim_end_matches('/')));
+    }
+
+    /// Serves HTML whole, identity encoded and without validators, so it can be rewritten per response.
+    pub fn set_dynamic_html(&mut self, dynamic_html: bool) {
+        self.dynamic_html = dynamic_html;
+    }
+
+    /// Origin that relative window urls are loaded from: the dev server in dev mode,
+    /// otherwise the first configured protocol.
+    pub fn base_url(&self) -> String {
+        if let Some(dev_url) = &self.dev_url {
+            return dev_url.clone();
+        }
+        let mount = &self.mounts[0];
+        format!("{}://{}/", mount.scheme, mount.host.as_deref().unwrap_or(&mount.scheme))
+    }
+
+    /// Directory roots with the url prefixes their pages load from, for live reload.
+    pub fn watch_targets(&self) -> Vec<(PathBuf, Vec<String>)> {
+        self.mounts
+            .iter()
+            .filter_map(|mount| {
+                let AssetSource::Dir(root) = &mount.source else {
+                    return None;
+                };
+                let scheme = &mount.scheme;
+                let origins = match &mount.host {
+                    Some(host) => vec![
+                        format!("{}://{}/", scheme, host),
+                        format!("http://{}.{}/", scheme, host),
+                        format!("https://{}.{}/", scheme, host),
+                    ],
+                    None => vec![
+                        format!("{}://", scheme),
+                        format!("http://{}.", scheme),
+                        format!("https://{}.", scheme),
+                    ],
+                };
+                Some((root.clone(), origins))
+            })
+            .collect()
+    }
+
+    /// Leaves absolute urls alone and resolves anything else against `base_url`.
+    pub fn resolve_url(&self, url: &str) -> String {
+        let is_absolute = url.split_once("://").is_some_and(|(scheme, _)| {
+            STANDARD_SCHEMES.contains(&scheme) || self.mounts.iter().any(|m| m.scheme == scheme)
+        });
+        if is_absolute {
+            url.to_string()
+        } else {
+            format!("{}{}", self.base_url(), url.trim_start_matches('/'))
+        }
+    }
+
+    fn find(&self, scheme: &str, host: &str) -> Option<&Mount> {
+        // Windows and Android rewrite `scheme://host` to `http://scheme.host`.
+        let prefix = format!("{}.", scheme);
+        let host = host.strip_prefix(&prefix).unwrap_or(host);
+        let mut mounts = self.mounts.iter().filter(|m| m.scheme == scheme);
+        mounts.clone().find(|m| m.host.as_deref() == Some(host))
+            .or_else(|| mounts.find(|m| m.host.is_none()))
+    }
+
+    /// Answers a request for one of the file-serving schemes.
+    pub fn handle(&self, scheme: &str, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+        let host = request.uri().host().unwrap_or_default().to_string();
+        let Some(mount) = self.find(scheme, &host) else {
+            return text_response(404, "Unknown host");
+        };
+        let encodings = match self.compression.precompressed() {
+            true => accepted_encodings(request_header(request, ACCEPT_ENCODING)),
+            false => vec![],
+        };
+        let resolved = match self.resolve(mount, request.uri().path(), &encodings) {
+            // A precompressed page cannot be rewritten, so serve the original instead.
+            Ok(Ok(asset)) if asset.encoding.is_some() && self.is_dynamic_html(&asset.path) => {
+                self.resolve(mount, request.uri().path(), &[])
+            }
+            resolved => resolved,
+        };
+        match resolved {
+            Ok(asset) => self.get_wry_response(request, asset),
+            Err(ResolveError::NotFound) => self.not_found_response(mount),
+            Err(ResolveError::Forbidden) => {
+                eprintln!("Refused asset request outside of the {} root: {}", scheme, request.uri());
+                text_response(403, "Forbidden")
+            }
+        }
+    }
+
+    /// Looks up `uri_path`, falling back to the entry document for client-side routes.
+    fn resolve<'a>(
+        &self,
+        mount: &'a Mount,
+        uri_path: &str,
+        encodings: &[Encoding],
+    ) -> Result<io::Result<Asset<'a>>, ResolveError> {
+        match (self.lookup(mount, uri_path, encodings), &self.fallback) {
+            (Err(ResolveError::NotFound), Some(fallback)) if is_route(uri_path) => {
+                self.lookup(mount, fallback, encodings)
+            }
+            (resolved, _) => resolved,
+        }
+    }
+
+    fn lookup<'a>(
+        &self,
+        mount: &'a Mount,
+        uri_path: &str,
+        encodings: &[Encoding],
+    ) -> Result<io::Result<Asset<'a>>, ResolveError> {
+        match &mount.source {
+            AssetSource::Dir(root) => resolve_asset(root, uri_path, mount.symlinks, &self.index)
+                .map(|file_path| open_precompressed(file_path, encodings)),
+            AssetSource::Bundle(bundle) => resolve_bundle_asset(bundle, uri_path, &self.index, encodings).map(Ok),
+        }
+    }
+
+    /// The configured 404 page, or a plain text body when there is none.
+    fn not_found_response(&self, mount: &Mount) -> Response<Vec<u8>> {
+        let page = self.not_found.as_ref().and_then(|page| match self.lookup(mount, page, &[]) {
+            Ok(Ok(mut asset)) => {
+                let etag = entity_tag(asset.len, asset.modified);
+                let content = self.read_all(&mut asset, &etag).ok()?;
+                Some((self.content_type(&asset.path), content))
+            }
+            _ => {
+                eprintln!("Not found page {} is missing", page);
+                None
+            }
+        });
+        match page {
+            Some((content_type, content)) => Response::builder()
+                .header(CONTENT_TYPE, content_type)
+                .header(CACHE_CONTROL, DEFAULT_CACHE_CONTROL)
+                .status(404)
+                .body(content)
+                .unwrap(),
+            None => text_response(404, "File not found"),
+        }
+    }
+
+    fn get_wry_response(&self, request: &Request<Vec<u8>>, asset: io::Result<Asset>) -> Response<Vec<u8>> {
+        println!("Assets:: {}", request.uri());
+        match asset.and_then(|asset| self.asset_response(request, asset)) {
+            Ok(response) => response,
+            Err(_) => text_response(500, "Failed to read the file"),
+        }
+    }
+
+    fn is_dynamic_html(&self, path: &Path) -> bool {
+        self.dynamic_html && self.content_type(path).starts_with("text/html")
+    }
+
+    fn asset_response(&self, request: &Request<Vec<u8>>, mut asset: Asset) -> io::Result<Response<Vec<u8>>> {
+        let (len, modified) = (asset.len, asset.modified);
+        let content_type = self.content_type(&asset.path);
+        if asset.encoding.is_none() && self.is_dynamic_html(&asset.path) {
+            let content = self.read_all(&mut asset, &entity_tag(len, modified))?;
+            return Ok(Response::builder()
+                .status(200)
+                .header(CONTENT_TYPE, content_type)
+                .header(CACHE_CONTROL, "no-store")
+                .body(content)
+                .unwrap());
+        }
+        let header = |name| request_header(request, name);
+
+        // Ranges address the stored bytes, so only whole responses are compressed on the fly.
+        let gzip_on_the_fly = asset.encoding.is_none()
+            && self.compression.gzip()
+            && header(RANGE).is_none()
+            && len >= self.compression.min_size()
+            && is_textual(&content_type)
+            && accepted_encodings(header(ACCEPT_ENCODING)).contains(&Encoding::Gzip);
+        let encoding = asset.encoding.or(gzip_on_the_fly.then_some(Encoding::Gzip));
+        let identity_etag = entity_tag(len, modified);
+        let etag = match encoding {
+            Some(encoding) => encoded_entity_tag(&identity_etag, encoding),
+            None => identity_etag.clone(),
+        };
+
+        let mut response = Response::builder()
+            .header(ETAG, &etag)
+            .header(CACHE_CONTROL, self.cache_control(request.uri().path()))
+            .header(VARY, ACCEPT_ENCODING.as_str());
+        if let Some(modified) = modified {
+            response = response.header(LAST_MODIFIED, httpdate::fmt_http_date(modified));
+        }
+        if is_not_modified(request.headers(), &etag, modified) {
+            return Ok(response.status(304).body(vec![]).unwrap());
+        }
+
+        let range = match (header(RANGE), header(IF_RANGE)) {
+            (Some(range), None) => parse_range(range, len),
+            (Some(range), Some(if_range)) if if_range_matches(if_range, &etag, modified) => parse_range(range, len),
+            _ => RangeRequest::Full,
+        };
+
+        let mut response = response
+            .header(CONTENT_TYPE, content_type)
+            .header(ACCEPT_RANGES, "bytes");
+        if let Some(encoding) = encoding {
+            response = response.header(CONTENT_ENCODING, encoding.token());
+        }
+        let response = match range {
+            RangeRequest::Full => {
+                let content = match gzip_on_the_fly {
+                    true => self.gzip_all(&mut asset, &identity_etag, &etag)?,
+                    false => self.read_all(&mut asset, &etag)?,
+                };
+                response.status(200).body(content)
+            }
+            RangeRequest::Partial(range) => {
+                let range = range.capped(MAX_RANGE_CHUNK);
+                let content = match &mut asset.body {
+                    AssetBody::File(file) => {
+                        let mut content = vec![0; range.len() as usize];
+                        file.seek(SeekFrom::Start(range.start))?;
+                        file.read_exact(&mut content)?;
+                        content
+                    }
+                    AssetBody::Memory(data) => data[range.start as usize..=range.end as usize].to_vec(),
+                };
+                response
+                    .status(206)
+                    .header(CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end, len))
+                    .body(content)
+            }
+            RangeRequest::Unsatisfiable => response
+                .status(416)
+                .header(CONTENT_RANGE, format!("bytes */{}", len))
+                .body(vec![]),
+        };
+        Ok(response.unwrap())
+    }
+
+    /// Whole asset contents, from the memory cache when it holds this version.
+    fn read_all(&self, asset: &mut Asset, etag: &str) -> io::Result<Vec<u8>> {
+        let key = asset.cache_key(asset.encoding);
+        let file = match &mut asset.body {
+            AssetBody::Memory(data) => return Ok(data.to_vec()),
+            AssetBody::File(file) => file,
+        };
+        let cache = self.cache.as_ref().filter(|cache| cache.accepts(asset.len));
+        if let Some(content) = cache.and_then(|cache| cache.get(&key, etag)) {
+            return Ok(content.as_ref().clone());
+        }
+        let mut content = Vec::with_capacity(asset.len as usize);
+        file.read_to_end(&mut content)?;
+        if let Some(cache) = cache {
+            cache.insert(&key, etag, Arc::new(content.clone()));
+        }
+        Ok(content)
+    }
+
+    /// Gzipped asset contents, cached separately from the identity ones.
+    fn gzip_all(&self, asset: &mut Asset, identity_etag: &str, etag: &str) -> io::Result<Vec<u8>> {
+        let key = asset.cache_key(Some(Encoding::Gzip));
+        if let Some(content) = self.cache.as_ref().and_then(|cache| cache.get(&key, etag)) {
+            return Ok(content.as_ref().clone());
+        }
+        let content = gzip(&self.read_all(asset, identity_etag)?)?;
+        if let Some(cache) = &self.cache {
+            cache.insert(&key, etag, Arc::new(content.clone()));
+        }
+        Ok(content)
+    }
+}
+
+/// Whether a request path looks like a client-side route rather than a missing asset.
+fn is_route(uri_path: &str) -> bool {
+    Path::new(uri_path).extension().is_none()
+}
+
+fn is_textual(mime: &str) -> bool {
+    let essence = mime.split(';').next().unwrap_or_default().trim();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(essence, "application/javascript" | "application/json" | "application/xml")
+}
+
+fn text_response(status: u16, body: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .header(CONTENT_TYPE, "text/plain")
+        .status(status)
+        .body(body.as_bytes().to_vec())
+        .unwrap()
+}
+
+fn request_header<T>(request: &Request<T>, name: http::header::HeaderName) -> Option<&str> {
+    request.headers().get(name).and_then(|value: &HeaderValue| value.to_str().ok())
+}
+
+/// `app.js` + `br` -> `app.js.br`
+fn with_extension_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn open_file(path: PathBuf) -> io::Result<Asset<'static>> {
+    let file = File::open(&path)?;
+    let metadata = file.metadata()?;
+    Ok(Asset {
+        path,
+        len: metadata.len(),
+        modified: metadata.modified().ok(),
+        encoding: None,
+        body: AssetBody::File(file),
+    })
+}
+
+/// Opens the first precompressed sibling of `path` in `encodings`, or `path` itself.
+///
+/// Siblings that are symlinks are ignored so they never widen what the symlink policy allows.
+fn open_precompressed(path: PathBuf, encodings: &[Encoding]) -> io::Result<Asset<'static>> {
+    for encoding in encodings {
+        let sibling = with_extension_suffix(&path, encoding.extension());
+        if fs::symlink_metadata(&sibling).is_ok_and(|meta| meta.is_file()) {
+            let asset = open_file(sibling)?;
+            return Ok(Asset { path, encoding: Some(*encoding), ..asset });
+        }
+    }
+    open_file(path)
+}
+
+/// Decodes a request path into its segments, refusing anything that could climb out of a root.
+fn request_segments(uri_path: &str) -> Result<Vec<String>, ResolveError> {
+    let decoded = percent_decode_str(uri_path)
+        .decode_utf8()
+        .map_err(|_| ResolveError::Forbidden)?;
+    if decoded.contains('\\') || decoded.contains('\0') {
+        return Err(ResolveError::Forbidden);
+    }
+    let mut segments = vec![];
+    for component in Path::new(decoded.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => segments.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            _ => return Err(ResolveError::Forbidden),
+        }
+    }
+    Ok(segments)
+}
+
+fn resolve_bundle_asset<'a>(
+    bundle: &'a Bundle,
+    uri_path: &str,
+    index: &str,
+    encodings: &[Encoding],
+) -> Result<Asset<'a>, ResolveError> {
+    let mut segments = request_segments(uri_path)?;
+    if bundle.get(&segments.join("/")).is_none() {
+        segments.push(index.to_string());
+    }
+    let name = segments.join("/");
+    let (data, modified) = bundle.get(&name).ok_or(ResolveError::NotFound)?;
+    let (data, modified, encoding) = encodings
+        .iter()
+        .find_map(|encoding| {
+            let (data, modified) = bundle.get(&format!("{}.{}", name, encoding.extension()))?;
+            Some((data, modified, Some(*encoding)))
+        })
+        .unwrap_or((data, modified, None));
+    Ok(Asset {
+        path: PathBuf::from(name),
+        len: data.len() as u64,
+        modified: Some(modified),
+        encoding,
+        body: AssetBody::Memory(data),
+    })
+}
+
+/// Maps a request path onto a file below `root`, refusing anything that would escape it.
+///
+/// Directories resolve to their `index` file.
+pub fn resolve_asset(root: &Path, uri_path: &str, symlinks: SymlinkPolicy, index: &str) -> Result<PathBuf, ResolveError> {
+    let mut segments = request_segments(uri_path)?;
+    let root = root.canonicalize().map_err(|_| ResolveError::NotFound)?;
+    let mut resolved = resolve_segments(&root, &segments, symlinks)?;
+    if resolved.is_dir() {
+        segments.push(index.to_string());
+        resolved = resolve_segments(&root, &segments, symlinks)?;
+    }
+    if !resolved.is_file() {
+        return Err(ResolveError::NotFound);
+    }
+    Ok(resolved)
+}
+
+fn resolve_segments(root: &Path, segments: &[String], symlinks: SymlinkPolicy) -> Result<PathBuf, ResolveError> {
+    let mut candidate = root.to_path_buf();
+    for segment in segments {
+        candidate.push(segment);
+        if symlinks == SymlinkPolicy::Deny {
+            match fs::symlink_metadata(&candidate) {
+                Ok(meta) if meta.file_type().is_symlink() => return Err(ResolveError::Forbidden),
+                Ok(_) => {}
+                Err(_) => return Err(ResolveError::NotFound),
+            }
+        }
+    }
+
+    let resolved = candidate.canonicalize().map_err(|_| ResolveError::NotFound)?;
+    if symlinks != SymlinkPolicy::Allow && !resolved.starts_with(root) {
+        return Err(ResolveError::Forbidden);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset_root() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("www")).unwrap();
+        fs::create_dir(dir.path().join("www/css")).unwrap();
+        fs::write(dir.path().join("www/index.html"), "<html></html>").unwrap();
+        fs::write(dir.path().join("www/css/app.css"), "body {}").unwrap();
+        fs::write(dir.path().join("www/hello world.txt"), "hi").unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        dir
+    }
+
+    fn resolve(dir: &tempfile::TempDir, path: &str, symlinks: SymlinkPolicy) -> Result<PathBuf, ResolveError> {
+        resolve_asset(&dir.path().join("www"), path, symlinks, DEFAULT_INDEX)
+    }
+
+    #[test]
+    fn resolves_files_inside_the_root() {
+        let dir = asset_root();
+        let resolved = resolve(&dir, "/css/app.css", SymlinkPolicy::WithinRoot).unwrap();
+        assert!(resolved.ends_with("www/css/app.css"));
+        let index = resolve(&dir, "/", SymlinkPolicy::WithinRoot).unwrap();
+        assert!(index.ends_with("www/index.html"));
+    }
+
+    #[test]
+    fn decodes_percent_encoded_paths() {
+        let dir = asset_root();
+        let resolved = resolve(&dir, "/hello%20world.txt", SymlinkPolicy::WithinRoot).unwrap();
+        assert!(resolved.ends_with("www/hello world.txt"));
+    }
+
+    #[test]
+    fn rejects_parent_directory_segments() {
+        let dir = asset_root();
+        for path in ["/../secret.txt", "/css/../../secret.txt", "/%2e%2e/secret.txt", "/..%2fsecret.txt"] {
+            assert_eq!(resolve(&dir, path, SymlinkPolicy::Allow), Err(ResolveError::Forbidden), "{}", path);
+        }
+    }
+
+    #[test]
+    fn rejects_backslashes_nul_bytes_and_invalid_utf8() {
+        let dir = asset_root();
+        for path in ["/..%5csecret.txt", "/index.html%00.png", "/%ff.html"] {
+            assert_eq!(resolve(&dir, path, SymlinkPolicy::WithinRoot), Err(ResolveError::Forbidden), "{}", path);
+        }
+    }
+
+    #[test]
+    fn reports_missing_files_and_directories_as_not_found() {
+        let dir = asset_root();
+        assert_eq!(resolve(&dir, "/missing.js", SymlinkPolicy::WithinRoot), Err(ResolveError::NotFound));
+        assert_eq!(resolve(&dir, "/css", SymlinkPolicy::WithinRoot), Err(ResolveError::NotFound));
+    }
+
+    #[test]
+    fn guesses_content_types_with_charset() {
+        let protocols = Protocols::new(&[], &AssetsConfig::default(), Path::new(".")).unwrap();
+        assert_eq!(protocols.content_type(Path::new("index.html")), "text/html; charset=utf-8");
+        assert_eq!(protocols.content_type(Path::new("app.mjs")), "text/javascript; charset=utf-8");
+        assert_eq!(protocols.content_type(Path::new("app.js.map")), "application/json; charset=utf-8");
+        assert_eq!(protocols.content_type(Path::new("logo.svg")), "image/svg+xml; charset=utf-8");
+        assert_eq!(protocols.content_type(Path::new("font.woff2")), "font/woff2");
+        assert_eq!(protocols.content_type(Path::new("clip.MP4")), "video/mp4");
+        assert_eq!(protocols.content_type(Path::new("blob")), "application/octet-stream");
+    }
+
+    #[test]
+    fn configured_mime_types_take_precedence() {
+        let assets = AssetsConfig {
+            mime_types: Some(HashMap::from([
+                (".md".to_string(), "text/plain".to_string()),
+                ("data".to_string(), "application/x-app-data".to_string()),
+            ])),
+            ..AssetsConfig::default()
+        };
+        let protocols = Protocols::new(&[], &assets, Path::new(".")).unwrap();
+        assert_eq!(protocols.content_type(Path::new("README.md")), "text/plain; charset=utf-8");
+        assert_eq!(protocols.content_type(Path::new("save.data")), "application/x-app-data");
+    }
+
+    #[test]
+    fn serves_files_from_a_bundle() {
+        let dir = asset_root();
+        let bundle_path = dir.path().join("app.pywui");
+        crate::bundle::pack_dir(&dir.path().join("www"), &bundle_path).unwrap();
+        let protocols = Protocols::new(&[], &AssetsConfig::default(), &bundle_path).unwrap();
+        let AssetSource::Bundle(bundle) = &protocols.mounts[0].source else {
+            panic!("expected a bundle mount");
+        };
+
+        let request = Request::get("pywui://pywui/css/app.css").header(RANGE, "bytes=5-").body(vec![]).unwrap();
+        let asset = resolve_bundle_asset(bundle, request.uri().path(), DEFAULT_INDEX, &[]).unwrap();
+        let response = protocols.asset_response(&request, asset).unwrap();
+        assert_eq!(response.status(), 206);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/css; charset=utf-8");
+        assert_eq!(response.body(), b"{}");
+
+        assert!(resolve_bundle_asset(bundle, "/", DEFAULT_INDEX, &[]).is_ok());
+        assert_eq!(resolve_bundle_asset(bundle, "/../secret.txt", DEFAULT_INDEX, &[]).err(), Some(ResolveError::Forbidden));
+        assert_eq!(resolve_bundle_asset(bundle, "/missing.js", DEFAULT_INDEX, &[]).err(), Some(ResolveError::NotFound));
+    }
+
+    #[test]
+    fn prefers_precompressed_siblings() {
+        let dir = asset_root();
+        fs::write(dir.path().join("www/css/app.css.gz"), "gzipped").unwrap();
+        let protocols = Protocols::new(&[], &AssetsConfig::default(), &dir.path().join("www")).unwrap();
+        let path = resolve(&dir, "/css/app.css", SymlinkPolicy::WithinRoot).unwrap();
+
+        let request = Request::get("pywui://pywui/css/app.css").header(ACCEPT_ENCODING, "gzip, br").body(vec![]).unwrap();
+        let asset = open_precompressed(path.clone(), &[Encoding::Brotli, Encoding::Gzip]).unwrap();
+        let response = protocols.asset_response(&request, asset).unwrap();
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/css; charset=utf-8");
+        assert_eq!(response.headers()[VARY], "accept-encoding");
+        assert_eq!(response.body(), b"gzipped");
+
+        let asset = open_precompressed(path, &[Encoding::Brotli]).unwrap();
+        let response = protocols.asset_response(&request, asset).unwrap();
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(response.body(), b"body {}");
+    }
+
+    #[test]
+    fn gzips_text_above_the_threshold() {
+        let dir = asset_root();
+        fs::write(dir.path().join("www/big.js"), "console.log(1);\n".repeat(200)).unwrap();
+        let assets = AssetsConfig {
+            compression: Some(CompressionConfig {
+                gzip: Some(true),
+                ..CompressionConfig::default()
+            }),
+            ..AssetsConfig::default()
+        };
+        let protocols = Protocols::new(&[], &assets, &dir.path().join("www")).unwrap();
+        let respond = |path: &str| {
+            let request = Request::get(format!("pywui://pywui{}", path)).header(ACCEPT_ENCODING, "gzip").body(vec![]).unwrap();
+            let file_path = resolve(&dir, path, SymlinkPolicy::WithinRoot).unwrap();
+            protocols.asset_response(&request, open_file(file_path).unwrap()).unwrap()
+        };
+
+        let big = respond("/big.js");
+        assert_eq!(big.headers()[CONTENT_ENCODING], "gzip");
+        assert!(big.headers()[ETAG].to_str().unwrap().ends_with("-gz\""));
+        assert!(big.body().len() < 3200);
+
+        let small = respond("/css/app.css");
+        assert!(!small.headers().contains_key(CONTENT_ENCODING));
+    }
+
+    #[test]
+    fn resolves_relative_urls_against_the_active_origin() {
+        let mut protocols = Protocols::new(&[], &AssetsConfig::default(), Path::new(".")).unwrap();
+        assert_eq!(protocols.resolve_url(""), "pywui://pywui/");
+        assert_eq!(protocols.resolve_url("/splash.html"), "pywui://pywui/splash.html");
+        assert_eq!(protocols.resolve_url("https://example.com/"), "https://example.com/");
+        protocols.set_dev_url("http://localhost:5173/");
+        assert_eq!(protocols.resolve_url("splash.html"), "http://localhost:5173/splash.html");
+        assert_eq!(protocols.resolve_url("pywui://pywui/app.js"), "pywui://pywui/app.js");
+    }
+
+    #[test]
+    fn resolves_directory_index_files() {
+        let dir = asset_root();
+        fs::write(dir.path().join("www/css/index.html"), "styles").unwrap();
+        let resolved = resolve(&dir, "/css/", SymlinkPolicy::WithinRoot).unwrap();
+        assert!(resolved.ends_with("www/css/index.html"));
+        let custom = resolve_asset(&dir.path().join("www"), "/", SymlinkPolicy::WithinRoot, "app.css");
+        assert_eq!(custom, Err(ResolveError::NotFound));
+    }
+
+    #[test]
+    fn falls_back_to_the_entry_document_for_routes() {
+        let dir = asset_root();
+        fs::write(dir.path().join("www/404.html"), "gone").unwrap();
+        let assets = AssetsConfig {
+            fallback: Some("index.html".to_string()),
+            not_found: Some("404.html".to_string()),
+            ..AssetsConfig::default()
+        };
+        let protocols = Protocols::new(&[], &assets, &dir.path().join("www")).unwrap();
+        let mount = &protocols.mounts[0];
+        let resolved_path = |path: &str| protocols.resolve(mount, path, &[]).map(|asset| asset.unwrap().path);
+
+        assert!(resolved_path("/settings/profile").unwrap().ends_with("www/index.html"));
+        assert!(resolved_path("/css/app.css").unwrap().ends_with("www/css/app.css"));
+        assert_eq!(resolved_path("/missing.js").err(), Some(ResolveError::NotFound));
+        assert_eq!(resolved_path("/../secret").err(), Some(ResolveError::Forbidden));
+
+        let response = protocols.not_found_response(mount);
+        assert_eq!(response.status(), 404);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+        assert_eq!(response.body(), b"gone");
+    }
+
+    #[test]
+    fn finds_the_mount_for_each_scheme_and_host() {
+        let dir = asset_root();
+        fs::create_dir(dir.path().join("media")).unwrap();
+        fs::write(dir.path().join("media/clip.txt"), "clip").unwrap();
+        let mount = |scheme: &str, host: Option<&str>, root: &str| ProtocolConfig {
+            scheme: scheme.to_string(),
+            host: host.map(str::to_string),
+            root: Some(dir.path().join(root).to_string_lossy().into_owned()),
+            symlinks: None,
+        };
+        let configs = [mount("app", Some("main"), "www"), mount("app", None, "www/css"), mount("media", None, "media")];
+        let protocols = Protocols::new(&configs, &AssetsConfig::default(), Path::new(".")).unwrap();
+        let root = |scheme: &str, host: &str| match &protocols.find(scheme, host)?.source {
+            AssetSource::Dir(root) => Some(root.clone()),
+            AssetSource::Bundle(_) => None,
+        };
+
+        assert_eq!(root("app", "main"), Some(dir.path().join("www")));
+        assert_eq!(root("app", "app.main"), Some(dir.path().join("www")));
+        assert_eq!(root("app", "other"), Some(dir.path().join("www/css")));
+        assert_eq!(root("app", ""), Some(dir.path().join("www/css")));
+        assert_eq!(root("media", "media.anything"), Some(dir.path().join("media")));
+        assert_eq!(root("pywui", "pywui"), None);
+
+        let get = |scheme: &str, url: &str| {
+            let response = protocols.handle(scheme, &Request::get(url).body(vec![]).unwrap());
+            (response.status().as_u16(), response.into_body())
+        };
+        assert_eq!(get("app", "app://main/index.html"), (200, b"<html></html>".to_vec()));
+        assert_eq!(get("app", "app://other/app.css"), (200, b"body {}".to_vec()));
+        assert_eq!(get("media", "media://media/clip.txt"), (200, b"clip".to_vec()));
+        assert_eq!(get("media", "media://media/index.html").0, 404);
+    }
+
+    #[test]
+    fn handles_the_rewritten_windows_urls() {
+        let dir = asset_root();
+        let configs = [
+            ProtocolConfig {
+                scheme: "app".to_string(),
+                host: Some("main".to_string()),
+                root: None,
+                symlinks: None,
+            },
+            ProtocolConfig {
+                scheme: "media".to_string(),
+                host: None,
+                root: None,
+                symlinks: None,
+            },
+        ];
+        let protocols = Protocols::new(&configs, &AssetsConfig::default(), &dir.path().join("www")).unwrap();
+        let get = |scheme: &str, url: &str| protocols.handle(scheme, &Request::get(url).body(vec![]).unwrap());
+
+        let page = get("app", "http://app.main/index.html");
+        assert_eq!(page.status(), 200);
+        assert_eq!(page.body(), b"<html></html>");
+        assert_eq!(get("app", "https://app.main/css/app.css").body(), b"body {}");
+        assert_eq!(get("app", "http://app.other/index.html").status(), 404);
+        assert_eq!(get("media", "http://media.localhost/css/app.css").body(), b"body {}");
+    }
+
+    #[test]
+    fn caps_range_responses_of_large_files() {
+        let dir = asset_root();
+        let size = MAX_RANGE_CHUNK as usize + 10;
+        fs::write(dir.path().join("www/movie.txt"), vec![b'a'; size]).unwrap();
+        let protocols = Protocols::new(&[], &AssetsConfig::default(), &dir.path().join("www")).unwrap();
+
+        let request = Request::get("pywui://pywui/movie.txt").body(vec![]).unwrap();
+        let whole = protocols.handle("pywui", &request);
+        assert_eq!(whole.status(), 200);
+        assert!(!whole.headers().contains_key(CONTENT_RANGE));
+        assert_eq!(whole.body().len(), size);
+
+        let request = Request::get("pywui://pywui/movie.txt").header(RANGE, "bytes=0-").body(vec![]).unwrap();
+        let first = protocols.handle("pywui", &request);
+        assert_eq!(first.status(), 206);
+        assert_eq!(first.headers()[CONTENT_RANGE], format!("bytes 0-{}/{}", MAX_RANGE_CHUNK - 1, size));
+        assert_eq!(first.body().len() as u64, MAX_RANGE_CHUNK);
+
+        let request = Request::get("pywui://pywui/movie.txt").header(RANGE, format!("bytes={}-", MAX_RANGE_CHUNK)).body(vec![]).unwrap();
+        let rest = protocols.handle("pywui", &request);
+        assert_eq!(rest.status(), 206);
+        assert_eq!(rest.body().len(), 10);
+    }
+
+    #[test]
+    fn serves_dynamic_html_whole_and_uncompressed() {
+        let dir = asset_root();
+        fs::write(dir.path().join("www/index.html.gz"), "gzipped").unwrap();
+        let mut protocols = Protocols::new(&[], &AssetsConfig::default(), &dir.path().join("www")).unwrap();
+        protocols.set_dynamic_html(true);
+        let request = Request::get("pywui://pywui/index.html")
+            .header(ACCEPT_ENCODING, "gzip")
+            .header(RANGE, "bytes=2-")
+            .header(http::header::IF_NONE_MATCH, "*")
+            .body(vec![])
+            .unwrap();
+
+        let page = protocols.handle("pywui", &request);
+        assert_eq!(page.status(), 200);
+        assert_eq!(page.headers()[CACHE_CONTROL], "no-store");
+        assert!(!page.headers().contains_key(CONTENT_ENCODING));
+        assert!(!page.headers().contains_key(ETAG));
+        assert_eq!(page.body(), b"<html></html>");
+
+        let request = Request::get("pywui://pywui/css/app.css").header(RANGE, "bytes=2-").body(vec![]).unwrap();
+        assert_eq!(protocols.handle("pywui", &request).status(), 206);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn applies_the_symlink_policy() {
+        use std::os::unix::fs::symlink;
+
+        let dir = asset_root();
+        symlink(dir.path().join("secret.txt"), dir.path().join("www/outside.txt")).unwrap();
+        symlink(dir.path().join("www/css/app.css"), dir.path().join("www/inside.css")).unwrap();
+
+        assert_eq!(resolve(&dir, "/outside.txt", SymlinkPolicy::WithinRoot), Err(ResolveError::Forbidden));
+        assert!(resolve(&dir, "/outside.txt", SymlinkPolicy::Allow).is_ok());
+        assert!(resolve(&dir, "/inside.css", SymlinkPolicy::WithinRoot).is_ok());
+        assert_eq!(resolve(&dir, "/inside.css", SymlinkPolicy::Deny), Err(ResolveError::Forbidden));
+        assert!(resolve(&dir, "/css/app.css", SymlinkPolicy::Deny).is_ok());
+    }
+}
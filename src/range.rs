@@ -0,0 +1,158 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Largest slice sent for a single range request, so seeking a multi-GB video
+/// never reads more than this into memory at once.
+pub const MAX_RANGE_CHUNK: u64 = 4 * 1024 * 1024;
+
+/// An inclusive byte range within a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Shortens the range to at most `max` bytes; clients ask again for the rest.
+    pub fn capped(self, max: u64) -> Self {
+        Self {
+            start: self.start,
+            end: self.end.min(self.start + max - 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No usable range, serve the whole file.
+    Full,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
+
+/// Parses a `Range` header against a file of `len` bytes.
+///
+/// Only a single `bytes` range is honoured; anything else falls back to the full body,
+/// which RFC 9110 allows.
+pub fn parse_range(header: &str, len: u64) -> RangeRequest {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let range = if start.is_empty() {
+        // `bytes=-N` asks for the last N bytes.
+        let Ok(suffix) = end.parse::<u64>() else {
+            return RangeRequest::Full;
+        };
+        if suffix == 0 || len == 0 {
+            return RangeRequest::Unsatisfiable;
+        }
+        ByteRange {
+            start: len.saturating_sub(suffix),
+            end: len - 1,
+        }
+    } else {
+        let Ok(start) = start.parse::<u64>() else {
+            return RangeRequest::Full;
+        };
+        let end = if end.is_empty() {
+            u64::MAX
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end,
+                _ => return RangeRequest::Full,
+            }
+        };
+        if start >= len {
+            return RangeRequest::Unsatisfiable;
+        }
+        ByteRange {
+            start,
+            end: end.min(len - 1),
+        }
+    };
+    RangeRequest::Partial(range)
+}
+
+/// Strong entity tag derived from the file size and modification time.
+pub fn entity_tag(len: u64, modified: Option<SystemTime>) -> String {
+    let modified = modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or(Duration::ZERO);
+    format!("\"{:x}-{:x}\"", len, modified.as_nanos())
+}
+
+/// Whether an `If-Range` validator still describes the file, so the range can be honoured.
+pub fn if_range_matches(if_range: &str, etag: &str, modified: Option<SystemTime>) -> bool {
+    let if_range = if_range.trim();
+    if if_range.starts_with('"') {
+        return if_range == etag;
+    }
+    if if_range.starts_with("W/") {
+        // Weak validators never satisfy If-Range.
+        return false;
+    }
+    match (httpdate::parse_http_date(if_range), modified) {
+        (Ok(date), Some(modified)) => httpdate::fmt_http_date(modified) == httpdate::fmt_http_date(date),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(start: u64, end: u64) -> RangeRequest {
+        RangeRequest::Partial(ByteRange { start, end })
+    }
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), partial(0, 99));
+        assert_eq!(parse_range("bytes=500-", 1000), partial(500, 999));
+        assert_eq!(parse_range("bytes=-100", 1000), partial(900, 999));
+        assert_eq!(parse_range("bytes=900-5000", 1000), partial(900, 999));
+        assert_eq!(parse_range("bytes=-5000", 1000), partial(0, 999));
+    }
+
+    #[test]
+    fn falls_back_to_the_full_body() {
+        for header in ["items=0-1", "bytes=0-1,4-5", "bytes=abc-", "bytes=5-1", "bytes=-"] {
+            assert_eq!(parse_range(header, 1000), RangeRequest::Full, "{}", header);
+        }
+    }
+
+    #[test]
+    fn rejects_ranges_past_the_end() {
+        assert_eq!(parse_range("bytes=1000-", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn caps_long_ranges() {
+        let range = ByteRange { start: 10, end: 1_000_000 }.capped(100);
+        assert_eq!(range, ByteRange { start: 10, end: 109 });
+        assert_eq!(range.len(), 100);
+    }
+
+    #[test]
+    fn compares_if_range_validators() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let etag = entity_tag(42, Some(modified));
+        assert!(if_range_matches(&etag, &etag, Some(modified)));
+        assert!(!if_range_matches("\"other\"", &etag, Some(modified)));
+        assert!(!if_range_matches(&format!("W/{}", etag), &etag, Some(modified)));
+        assert!(if_range_matches(&httpdate::fmt_http_date(modified), &etag, Some(modified)));
+        assert!(!if_range_matches("Tue, 15 Nov 1994 08:12:31 GMT", &etag, Some(modified)));
+    }
+}
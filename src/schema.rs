@@ -0,0 +1,165 @@
+use schemars::schema_for;
+use serde_json::{Map, Value};
+
+use crate::config::{Config, ConfigError};
+
+/// Keys allowed at the top of a config file although `Config` does not read them.
+const EDITOR_KEYS: [&str; 1] = ["$schema"];
+
+/// JSON Schema (draft 7) of `pywui.conf.json`, for editor completion and validation.
+pub fn config_schema() -> Value {
+    serde_json::to_value(schema_for!(Config)).expect("The config schema is valid JSON")
+}
+
+/// Rejects keys the config structs would silently ignore, suggesting the closest known key.
+pub fn check_unknown_keys(config: &Value) -> Result<(), ConfigError> {
+    let schema = config_schema();
+    let definitions = schema.get("definitions").and_then(Value::as_object).cloned().unwrap_or_default();
+    let checker = Checker { definitions };
+    checker.check(config, &schema, ".", true)
+}
+
+struct Checker {
+    definitions: Map<String, Value>,
+}
+
+impl Checker {
+    fn check(&self, value: &Value, schema: &Value, path: &str, is_root: bool) -> Result<(), ConfigError> {
+        let schemas = self.flatten(schema);
+        match value {
+            Value::Object(object) => {
+                let properties: Map<String, Value> = schemas
+                    .iter()
+                    .filter_map(|schema| schema.get("properties").and_then(Value::as_object))
+                    .flat_map(|properties| properties.clone())
+                    .collect();
+                let extra = schemas.iter().find_map(|schema| schema.get("additionalProperties"));
+                for (key, item) in object {
+                    let item_path = join(path, key);
+                    if let Some(property) = properties.get(key) {
+                        self.check(item, property, &item_path, false)?;
+                    } else if let Some(extra) = extra.filter(|extra| extra.is_object()) {
+                        self.check(item, extra, &item_path, false)?;
+                    } else if extra != Some(&Value::Bool(true)) && !(is_root && EDITOR_KEYS.contains(&key.as_str())) {
+                        let mut message = format!("unknown field `{}`", key);
+                        if let Some(suggestion) = did_you_mean(key, properties.keys()) {
+                            message.push_str(&format!(", did you mean `{}`?", suggestion));
+                        }
+                        return Err(ConfigError {
+                            file: None,
+                            path: item_path,
+                            message,
+                        });
+                    }
+                }
+                Ok(())
+            }
+            Value::Array(items) => {
+                // Tuples list one schema per position; their items are never objects here.
+                let item_schema = schemas.iter().find_map(|schema| schema.get("items"));
+                let Some(item_schema) = item_schema.filter(|schema| schema.is_object()) else {
+                    return Ok(());
+                };
+                for (index, item) in items.iter().enumerate() {
+                    self.check(item, item_schema, &format!("{}[{}]", path.trim_end_matches('.'), index), false)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The schema and everything it refers to or combines, so their properties can be merged.
+    fn flatten<'a>(&'a self, schema: &'a Value) -> Vec<&'a Value> {
+        let mut schemas = vec![];
+        if let Some(definition) = schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| reference.strip_prefix("#/definitions/"))
+            .and_then(|name| self.definitions.get(name))
+        {
+            schemas.extend(self.flatten(definition));
+        }
+        for combinator in ["allOf", "anyOf", "oneOf"] {
+            for schema in schema.get(combinator).and_then(Value::as_array).into_iter().flatten() {
+                schemas.extend(self.flatten(schema));
+            }
+        }
+        schemas.push(schema);
+        schemas
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    match path {
+        "." => key.to_string(),
+        _ => format!("{}.{}", path, key),
+    }
+}
+
+/// The known key closest to `key`, ignoring case and `_`, when it is close enough to be a typo.
+fn did_you_mean<'a>(key: &str, known: impl Iterator<Item = &'a String>) -> Option<&'a str> {
+    let normalize = |key: &str| key.replace('_', "").to_ascii_lowercase();
+    let key = normalize(key);
+    let max_distance = (key.len() / 3).max(2);
+    known
+        .map(|candidate| (strsim::levenshtein(&key, &normalize(candidate)), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn describes_the_config_structs() {
+        let schema = config_schema();
+        let definitions = &schema["definitions"];
+        assert!(schema["properties"]["build"].is_object());
+        assert!(definitions["WindowAttributesConfig"]["properties"]["always_on_top"].is_object());
+        assert!(definitions["WebViewAttributesConfig"]["properties"]["user_agent"].is_object());
+        assert!(definitions["Build"]["properties"]["buildPath"].is_object());
+    }
+
+    #[test]
+    fn suggests_known_keys_for_typos() {
+        let config = json!({
+            "$schema": "./pywui.schema.json",
+            "pywui": {"windows": [{"label": "main"}, {"label": "other", "alwaysOnTop": true}]},
+        });
+        let error = check_unknown_keys(&config).unwrap_err();
+        assert_eq!(error.path, "pywui.windows[1].alwaysOnTop");
+        assert_eq!(error.message, "unknown field `alwaysOnTop`, did you mean `always_on_top`?");
+
+        let error = check_unknown_keys(&json!({"biuld": {}})).unwrap_err();
+        assert_eq!(error.message, "unknown field `biuld`, did you mean `build`?");
+        let error = check_unknown_keys(&json!({"pywui": {"windows": [{"center": true}]}})).unwrap_err();
+        assert_eq!(error.message, "unknown field `center`");
+    }
+
+    #[test]
+    fn accepts_known_and_free_form_keys() {
+        let config = json!({
+            "build": {"devPath": "http://localhost:5173", "mode": "dev"},
+            "pywui": {"windows": [{
+                "inner_size": {"Logical": {"width": 800.0, "height": 600.0}},
+                "webview": {"url": "index.html"},
+            }]},
+            "assets": {
+                "mimeTypes": {"wasm": "application/wasm"},
+                "cacheControl": [{"pattern": "*", "value": "no-cache"}],
+            },
+            "security": {"headers": [{"pattern": "*", "headers": {"X-Frame-Options": "DENY"}}]},
+        });
+        assert_eq!(check_unknown_keys(&config), Ok(()));
+        let window = |inner_size| json!({"pywui": {"windows": [{"inner_size": inner_size}]}});
+        let error = check_unknown_keys(&window(json!({"Logical": {"widht": 1}}))).unwrap_err();
+        assert_eq!(error.path, "pywui.windows[0].inner_size.Logical.widht");
+        let error = check_unknown_keys(&window(json!({"Logicl": {}}))).unwrap_err();
+        assert_eq!(error.message, "unknown field `Logicl`, did you mean `Logical`?");
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use http::header::{CACHE_CONTROL, CONTENT_ENCODING, CONTENT_SECURITY_POLICY, CONTENT_TYPE, ETAG, LAST_MODIFIED, X_CONTENT_TYPE_OPTIONS};
+use http::{HeaderName, HeaderValue, Response};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::util::matches_pattern;
+
+const NONCE_PLACEHOLDER: &str = "{nonce}";
+
+/// Security headers added to every response of the custom protocols.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct SecurityConfig {
+    /// `Content-Security-Policy`; `{nonce}` is replaced with a fresh nonce for every response, which is
+    /// also added to the `<script>` and `<style>` tags of served HTML.
+    pub csp: Option<String>,
+    /// Sends `X-Content-Type-Options: nosniff`. Defaults to true.
+    pub nosniff: Option<bool>,
+    /// Sends the COOP/COEP pair that enables `SharedArrayBuffer` and WASM threads.
+    #[serde(rename = "crossOriginIsolation")]
+    pub cross_origin_isolation: Option<bool>,
+    /// Extra headers for request paths matching `pattern`; every matching rule applies.
+    pub headers: Option<Vec<HeaderRule>>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+pub struct HeaderRule {
+    pub pattern: String,
+    pub headers: HashMap<String, String>,
+}
+
+/// The parsed headers of a [`SecurityConfig`], ready to be stamped onto responses.
+///
+/// Values keep their `{nonce}` placeholder until a response is stamped.
+#[derive(Debug, Default)]
+pub struct SecurityHeaders {
+    global: Vec<(HeaderName, String)>,
+    rules: Vec<(String, Vec<(HeaderName, String)>)>,
+}
+
+impl SecurityHeaders {
+    /// Fails on header names or values that are not valid HTTP.
+    pub fn new(config: &SecurityConfig) -> Result<Self, String> {
+        let mut global = vec![];
+        if let Some(csp) = &config.csp {
+            global.push((CONTENT_SECURITY_POLICY, header_template(csp)?));
+        }
+        if config.nosniff.unwrap_or(true) {
+            global.push((X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()));
+        }
+        if config.cross_origin_isolation.unwrap_or(false) {
+            global.push((HeaderName::from_static("cross-origin-opener-policy"), "same-origin".to_string()));
+            global.push((HeaderName::from_static("cross-origin-embedder-policy"), "require-corp".to_string()));
+        }
+        let mut rules = vec![];
+        for rule in config.headers.iter().flatten() {
+            let mut headers = vec![];
+            for (name, value) in &rule.headers {
+                let name = HeaderName::try_from(name.as_str())
+                    .map_err(|_| format!("Invalid header name '{}' for pattern '{}'", name, rule.pattern))?;
+                headers.push((name, header_template(value)?));
+            }
+            rules.push((rule.pattern.clone(), headers));
+        }
+        Ok(Self { global, rules })
+    }
+
+    /// Whether some header asks for a nonce, which makes served HTML differ on every response.
+    pub fn uses_nonce(&self) -> bool {
+        self.global
+            .iter()
+            .chain(self.rules.iter().flat_map(|(_, headers)| headers))
+            .any(|(_, value)| value.contains(NONCE_PLACEHOLDER))
+    }
+
+    /// Adds the headers for `uri_path`, leaving any the response already sets alone.
+    ///
+    /// When a nonce is needed, a fresh one is generated and added to the tags of an HTML body, which
+    /// then must not be cached.
+    pub fn apply<B>(&self, uri_path: &str, response: &mut Response<B>)
+    where
+        B: AsRef<[u8]> + From<Vec<u8>>,
+    {
+        let nonce = self.uses_nonce().then(generate_nonce);
+        if let Some(nonce) = nonce.as_deref().filter(|_| is_rewritable_html(response)) {
+            if let Some(body) = add_nonce(response.body().as_ref(), nonce) {
+                *response.body_mut() = B::from(body);
+                let headers = response.headers_mut();
+                headers.remove(ETAG);
+                headers.remove(LAST_MODIFIED);
+                headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+            }
+        }
+        let matching = self
+            .rules
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, uri_path))
+            .flat_map(|(_, headers)| headers);
+        let headers = response.headers_mut();
+        for (name, value) in self.global.iter().chain(matching) {
+            if headers.contains_key(name) {
+                continue;
+            }
+            let value = match &nonce {
+                Some(nonce) => value.replace(NONCE_PLACEHOLDER, nonce),
+                None => value.clone(),
+            };
+            // Checked by `header_template`, and a hex nonce keeps the value valid.
+            if let Ok(value) = HeaderValue::try_from(value) {
+                headers.insert(name.clone(), value);
+            }
+        }
+    }
+}
+
+/// Validates a header value as it will be sent once its placeholder is filled.
+fn header_template(value: &str) -> Result<String, String> {
+    HeaderValue::try_from(value.replace(NONCE_PLACEHOLDER, "0"))
+        .map_err(|_| format!("Invalid header value '{}'", value))?;
+    Ok(value.to_string())
+}
+
+/// Full, uncompressed HTML documents; anything else cannot be rewritten.
+fn is_rewritable_html<B>(response: &Response<B>) -> bool {
+    let headers = response.headers();
+    let is_html = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim_start().to_ascii_lowercase().starts_with("text/html"));
+    is_html && !headers.contains_key(CONTENT_ENCODING) && !matches!(response.status().as_u16(), 206 | 304)
+}
+
+/// Adds `nonce="…"` to every `<script>` and `<style>` tag that has none, or `None` when `html`
+/// is not UTF-8.
+///
+/// Comments and the raw text inside script and style elements are skipped, so markup in JS strings
+/// is left alone.
+fn add_nonce(html: &[u8], nonce: &str) -> Option<Vec<u8>> {
+    let html = std::str::from_utf8(html).ok()?;
+    let lowercase = html.to_ascii_lowercase();
+    let skip_to = |from: usize, end: &str| lowercase[from..].find(end).map_or(lowercase.len(), |at| from + at + end.len());
+    let mut output = String::with_capacity(html.len() + 64);
+    let mut copied = 0;
+    let mut position = 0;
+    while let Some(offset) = lowercase[position..].find('<') {
+        let start = position + offset;
+        if lowercase[start..].starts_with("<!--") {
+            position = skip_to(start + 4, "-->");
+            continue;
+        }
+        position = start + 1;
+        let Some(name) = ["script", "style"].into_iter().find(|name| lowercase[start + 1..].starts_with(name)) else {
+            continue;
+        };
+        let name_end = start + 1 + name.len();
+        if !lowercase[name_end..].starts_with(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/') {
+            continue;
+        }
+        let tag_end = skip_to(name_end, ">");
+        if !lowercase[name_end..tag_end].contains("nonce") {
+            output.push_str(&html[copied..name_end]);
+            output.push_str(&format!(" nonce=\"{}\"", nonce));
+            copied = name_end;
+        }
+        position = skip_to(tag_end, &format!("</{}", name));
+    }
+    output.push_str(&html[copied..]);
+    Some(output.into_bytes())
+}
+
+/// A fresh random nonce, hex encoded.
+pub fn generate_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("Failed to gather randomness for the CSP nonce");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(content_type: &str, body: &str) -> Response<Vec<u8>> {
+        Response::builder()
+            .header(CONTENT_TYPE, content_type)
+            .header(ETAG, "\"1-2\"")
+            .body(body.as_bytes().to_vec())
+            .unwrap()
+    }
+
+    #[test]
+    fn applies_global_and_matching_headers() {
+        let config = SecurityConfig {
+            csp: Some("default-src 'self'".to_string()),
+            cross_origin_isolation: Some(true),
+            headers: Some(vec![HeaderRule {
+                pattern: "*.wasm".to_string(),
+                headers: HashMap::from([("Cache-Control".to_string(), "immutable".to_string())]),
+            }]),
+            ..SecurityConfig::default()
+        };
+        let security = SecurityHeaders::new(&config).unwrap();
+        assert!(!security.uses_nonce());
+
+        let mut page = response("text/html", "<script>1</script>");
+        security.apply("/index.html", &mut page);
+        let headers = page.headers();
+        assert_eq!(headers[CONTENT_SECURITY_POLICY], "default-src 'self'");
+        assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers["cross-origin-opener-policy"], "same-origin");
+        assert_eq!(headers["cross-origin-embedder-policy"], "require-corp");
+        assert!(!headers.contains_key("cache-control"));
+        assert_eq!(page.body(), b"<script>1</script>");
+
+        let mut wasm = response("application/wasm", "");
+        wasm.headers_mut().insert("cache-control", HeaderValue::from_static("no-store"));
+        security.apply("/pkg/app.wasm", &mut wasm);
+        assert_eq!(wasm.headers()["cache-control"], "no-store");
+        let mut wasm = response("application/wasm", "");
+        security.apply("/pkg/app.wasm", &mut wasm);
+        assert_eq!(wasm.headers()["cache-control"], "immutable");
+    }
+
+    #[test]
+    fn adds_a_fresh_nonce_to_each_html_response() {
+        let config = SecurityConfig {
+            csp: Some("script-src 'nonce-{nonce}'".to_string()),
+            ..SecurityConfig::default()
+        };
+        let security = SecurityHeaders::new(&config).unwrap();
+        assert!(security.uses_nonce());
+
+        let html = "<html><SCRIPT src=\"a.js\"></SCRIPT><script nonce=\"x\"></script><scripts><style>p{}</style>";
+        let mut first = response("text/html; charset=utf-8", html);
+        security.apply("/", &mut first);
+        let csp = first.headers()[CONTENT_SECURITY_POLICY].to_str().unwrap().to_string();
+        let nonce = csp.strip_prefix("script-src 'nonce-").unwrap().trim_end_matches('\'');
+        assert_eq!(
+            String::from_utf8(first.body().clone()).unwrap(),
+            format!(
+                "<html><SCRIPT nonce=\"{0}\" src=\"a.js\"></SCRIPT><script nonce=\"x\"></script><scripts><style nonce=\"{0}\">p{{}}</style>",
+                nonce
+            )
+        );
+        assert!(!first.headers().contains_key(ETAG));
+        assert_eq!(first.headers()[CACHE_CONTROL], "no-store");
+
+        let mut second = response("text/html", html);
+        security.apply("/", &mut second);
+        assert_ne!(second.headers()[CONTENT_SECURITY_POLICY], csp.as_str());
+
+        let tricky = "<!-- <script> --><script>var s = \"<script src=x>\"; // </style>\n</script><style>a::after { content: '<style>' }</style>";
+        let mut page = response("text/html", tricky);
+        security.apply("/", &mut page);
+        let nonce = page.headers()[CONTENT_SECURITY_POLICY].to_str().unwrap().replace("script-src 'nonce-", "").replace('\'', "");
+        assert_eq!(
+            String::from_utf8(page.body().clone()).unwrap(),
+            format!(
+                "<!-- <script> --><script nonce=\"{0}\">var s = \"<script src=x>\"; // </style>\n</script><style nonce=\"{0}\">a::after {{ content: '<style>' }}</style>",
+                nonce
+            )
+        );
+
+        let mut compressed = response("text/html", html);
+        compressed.headers_mut().insert(CONTENT_ENCODING, HeaderValue::from_static("br"));
+        security.apply("/", &mut compressed);
+        assert_eq!(compressed.body(), html.as_bytes());
+        assert!(compressed.headers().contains_key(ETAG));
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let config = SecurityConfig {
+            headers: Some(vec![HeaderRule {
+                pattern: "*".to_string(),
+                headers: HashMap::from([("bad header".to_string(), "x".to_string())]),
+            }]),
+            ..SecurityConfig::default()
+        };
+        assert!(SecurityHeaders::new(&config).is_err());
+        assert!(SecurityHeaders::new(&SecurityConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn generates_distinct_nonces() {
+        let nonce = generate_nonce();
+        assert_eq!(nonce.len(), 32);
+        assert_ne!(nonce, generate_nonce());
+    }
+}
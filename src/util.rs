@@ -1,15 +1,19 @@
 use pyo3::{IntoPy, PyObject, Python};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
-use serde_json::{Number, Result, Value};
+use serde_json::{Number, Value};
 
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
 
-pub fn load_py_config(config: PyObject) -> Result<Config> {
+pub fn load_py_config(config: PyObject) -> Result<Config, ConfigError> {
     Python::with_gil(|py| {
-        let value: Value = py_to_json(py, config);
-        let config: Config = serde_json::from_value(value).unwrap();
-        Ok(config)
+        let value = py_to_json(py, config).map_err(|e| ConfigError {
+            file: None,
+            path: ".".to_string(),
+            message: e.value(py).to_string(),
+        })?;
+        Config::from_value(value)
     })
 }
 
@@ -41,45 +45,120 @@ pub fn json_to_py(py: Python, value: &Value) -> PyObject {
     }
 }
 
+/// Describes a Python exception as `{type, message, traceback}` for the JS bridge.
+pub fn py_err_to_json(py: Python, err: &PyErr) -> Value {
+    let error_type = err
+        .get_type(py)
+        .qualname()
+        .map(|name| name.to_string())
+        .unwrap_or_else(|_| "Exception".to_string());
+    let traceback = err.traceback(py).and_then(|tb| tb.format().ok());
+    serde_json::json!({
+        "type": error_type,
+        "message": err.value(py).to_string(),
+        "traceback": traceback,
+    })
+}
 
-pub fn py_to_json(py: Python, obj: PyObject) -> Value {
+
+/// Converts a Python value to JSON; numbers JSON cannot represent, such as `nan` or `2**70`, are errors.
+pub fn py_to_json(py: Python, obj: PyObject) -> PyResult<Value> {
     if obj.is(&py.None()) {
-        return Value::Null;
+        return Ok(Value::Null);
     }
 
-    if let Some(py_bool) = obj.downcast_bound::<pyo3::types::PyBool>(py).ok() {
-        return Value::Bool(py_bool.is_true());
+    if let Ok(py_bool) = obj.downcast_bound::<pyo3::types::PyBool>(py) {
+        return Ok(Value::Bool(py_bool.is_true()));
     }
 
-    if let Some(py_int) = obj.downcast_bound::<pyo3::types::PyLong>(py).ok() {
-        return Value::Number(py_int.extract::<i64>().unwrap().into());
+    if let Ok(py_int) = obj.downcast_bound::<pyo3::types::PyLong>(py) {
+        if let Ok(value) = py_int.extract::<i64>() {
+            return Ok(Value::Number(value.into()));
+        }
+        return Ok(Value::Number(py_int.extract::<u64>()?.into()));
     }
 
-    if let Some(py_float) = obj.downcast_bound::<pyo3::types::PyFloat>(py).ok() {
-        return Value::Number(Number::from_f64(py_float.extract::<f64>().unwrap()).unwrap());
+    if let Ok(py_float) = obj.downcast_bound::<pyo3::types::PyFloat>(py) {
+        let value = py_float.value();
+        return Number::from_f64(value)
+            .map(Value::Number)
+            .ok_or_else(|| PyValueError::new_err(format!("{} cannot be represented in JSON", value)));
     }
 
-    if let Some(py_str) = obj.downcast_bound::<pyo3::types::PyString>(py).ok() {
-        return Value::String(py_str.to_str().unwrap().to_string());
+    if let Ok(py_str) = obj.downcast_bound::<pyo3::types::PyString>(py) {
+        return Ok(Value::String(py_str.to_str()?.to_string()));
     }
 
-    if let Some(py_list) = obj.downcast_bound::<PyList>(py).ok() {
-        let arr: Vec<Value> = py_list
+    if let Ok(py_list) = obj.downcast_bound::<PyList>(py) {
+        let arr = py_list
             .iter()
             .map(|item| py_to_json(py, item.into()))
-            .collect();
-        return Value::Array(arr);
+            .collect::<PyResult<Vec<Value>>>()?;
+        return Ok(Value::Array(arr));
     }
 
-    if let Some(py_dict) = obj.downcast_bound::<PyDict>(py).ok() {
+    if let Ok(py_dict) = obj.downcast_bound::<PyDict>(py) {
         let mut obj_map = serde_json::Map::new();
         for (key, value) in py_dict {
             let key_str = key.to_string();
-            obj_map.insert(key_str, py_to_json(py, value.into()));
+            obj_map.insert(key_str, py_to_json(py, value.into())?);
         }
-        return Value::Object(obj_map);
+        return Ok(Value::Object(obj_map));
     }
 
-    Value::Null
+    Ok(Value::Null)
+}
+
+/// Glob-style match where `*` spans any run of characters (including `/`) and `?` one character.
+pub fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn converts_python_values_to_json() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let convert = |code: &std::ffi::CStr| py_to_json(py, py.eval(code, None, None).unwrap().unbind());
+            let value = convert(c"{'a': [1, 2.5, 'x', None, True], 'big': 2**64 - 1}").unwrap();
+            assert_eq!(value, json!({"a": [1, 2.5, "x", null, true], "big": u64::MAX}));
+            assert!(convert(c"float('nan')").unwrap_err().to_string().contains("cannot be represented in JSON"));
+            assert!(convert(c"[2**70]").unwrap_err().is_instance_of::<pyo3::exceptions::PyOverflowError>(py));
+            assert!(convert(c"'\\udc80'").is_err());
+        });
+    }
+
+    #[test]
+    fn matches_glob_patterns() {
+        assert!(matches_pattern("*.js", "/assets/app.js"));
+        assert!(matches_pattern("/assets/*", "/assets/css/app.css"));
+        assert!(matches_pattern("/index.html", "/index.html"));
+        assert!(matches_pattern("/app.??.js", "/app.v2.js"));
+        assert!(!matches_pattern("*.js", "/assets/app.json"));
+        assert!(!matches_pattern("/assets/*", "/index.html"));
+    }
+}
@@ -1,8 +1,13 @@
 use std::collections::HashMap;
+use http::Uri;
+use schemars::JsonSchema;
 use serde::Deserialize;
-use wry::{WebViewAttributes, WebViewId};
+use serde_json::Value;
+use wry::{WebView, WebViewAttributes};
 
-#[derive(Deserialize, Debug, Default, Clone)]
+use crate::protocol::Protocols;
+
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 pub struct WebViewAttributesConfig {
     pub user_agent: Option<String>,
     pub visible: Option<bool>,
@@ -21,16 +26,6 @@ pub struct WebViewAttributesConfig {
     pub focused: Option<bool>,
 }
 
-fn ensure_valid_url(url: Option<String>) -> Option<String> {
-    url.map(|mut u| {
-        let valid_protocols = ["http://", "https://", "ftp://", "file://", "ws://", "wss://"];
-        if !valid_protocols.iter().any(|protocol| u.starts_with(protocol)) {
-            u = format!("pywui://pywui/{}", u);
-        }
-        u
-    })
-}
-
 impl From<WebViewAttributesConfig> for WebViewAttributes<'static> {
     fn from(config: WebViewAttributesConfig) -> Self {
         WebViewAttributes {
@@ -38,7 +33,7 @@ impl From<WebViewAttributesConfig> for WebViewAttributes<'static> {
             visible: config.visible.unwrap_or(true),
             transparent: config.transparent.unwrap_or(false),
             background_color: config.background_color,
-            url: ensure_valid_url(config.url),
+            url: config.url,
             zoom_hotkeys_enabled: config.zoom_hotkeys_enabled.unwrap_or(true),
             html: config.html,
             initialization_scripts: config.initialization_scripts.unwrap_or_default(),
@@ -55,3 +50,101 @@ impl From<WebViewAttributesConfig> for WebViewAttributes<'static> {
         }
     }
 }
+
+/// Changes that can be applied to a live webview through `WindowManager::update_webview`.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebViewUpdate {
+    pub url: Option<String>,
+    pub html: Option<String>,
+    pub script: Option<String>,
+    pub visible: Option<bool>,
+    pub focus: Option<bool>,
+    pub devtools: Option<bool>,
+    pub clear: Option<bool>,
+}
+
+impl WebViewUpdate {
+    pub fn from_value(value: Value, protocols: &Protocols) -> Result<Self, String> {
+        let mut update: WebViewUpdate = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        update.url = update.url.map(|url| protocols.resolve_url(&url));
+        if let Some(url) = &update.url {
+            match url.parse::<Uri>() {
+                Ok(uri) if uri.scheme().is_some() => {}
+                _ => return Err(format!("invalid url `{}`", url)),
+            }
+        }
+        Ok(update)
+    }
+
+    /// Applies everything except `script`, which the caller evaluates last.
+    pub fn apply(&self, webview: &WebView) -> wry::Result<()> {
+        if let Some(url) = &self.url {
+            webview.load_url(url)?;
+        }
+        if let Some(html) = &self.html {
+            webview.load_html(html)?;
+        }
+        if let Some(visible) = self.visible {
+            webview.set_visible(visible)?;
+        }
+        if let Some(true) = self.focus {
+            webview.focus()?;
+        }
+        if let Some(devtools) = self.devtools {
+            if devtools {
+                webview.open_devtools();
+            } else {
+                webview.close_devtools();
+            }
+        }
+        if let Some(true) = self.clear {
+            webview.clear_all_browsing_data()?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `script` as a function body so that its `return` value or thrown error comes back as
+/// `{data}` / `{error}`, without needing `'unsafe-eval'` in the CSP.
+pub fn wrap_script(script: &str) -> String {
+    format!(
+        "(() => {{ try {{ return {{ data: (function () {{\n{}\n}})() }}; }} catch (e) {{ return {{ error: String(e) }}; }} }})()",
+        script
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn protocols() -> Protocols {
+        Protocols::new(&[], &Default::default(), std::path::Path::new(".")).unwrap()
+    }
+
+    #[test]
+    fn resolves_and_validates_urls() {
+        let update = WebViewUpdate::from_value(json!({"url": "about.html"}), &protocols()).unwrap();
+        assert_eq!(update.url.as_deref(), Some("pywui://pywui/about.html"));
+        let update = WebViewUpdate::from_value(json!({"url": "https://example.com/a"}), &protocols()).unwrap();
+        assert_eq!(update.url.as_deref(), Some("https://example.com/a"));
+        assert!(WebViewUpdate::from_value(json!({"url": "has spaces"}), &protocols()).is_err());
+        assert!(WebViewUpdate::from_value(json!({"url": 1}), &protocols()).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let error = WebViewUpdate::from_value(json!({"scripts": "1"}), &protocols()).unwrap_err();
+        assert!(error.contains("unknown field `scripts`"), "{}", error);
+        assert!(WebViewUpdate::from_value(json!({"script": "return 1", "focus": true}), &protocols()).is_ok());
+    }
+
+    #[test]
+    fn wraps_scripts_as_a_function_body() {
+        let wrapped = wrap_script("return 1; // done");
+        assert!(!wrapped.contains("eval"));
+        assert!(wrapped.contains("(function () {\nreturn 1; // done\n})()"));
+    }
+}
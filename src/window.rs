@@ -1,12 +1,15 @@
+use schemars::JsonSchema;
 use serde::Deserialize;
-use tao::dpi::Size;
-use tao::window::WindowAttributes;
+use serde_json::Value;
+use tao::dpi::{LogicalSize, Size};
+use tao::window::{Fullscreen, Window, WindowAttributes};
 
 use crate::webview::WebViewAttributesConfig;
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 pub struct WindowAttributesConfig {
     pub label: Option<String>,
+    #[schemars(with = "Option<SizeSchema>")]
     pub inner_size: Option<Size>,
     pub resizable: Option<bool>,
     pub minimizable: Option<bool>,
@@ -28,6 +31,14 @@ pub struct WindowAttributesConfig {
     pub webview: Option<WebViewAttributesConfig>,
 }
 
+/// The serialized shape of [`Size`], which has no schema of its own.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+enum SizeSchema {
+    Physical { width: u32, height: u32 },
+    Logical { width: f64, height: f64 },
+}
+
 impl From<WindowAttributesConfig> for WindowAttributes {
     fn from(config: WindowAttributesConfig) -> Self {
         WindowAttributes {
@@ -51,3 +62,151 @@ impl From<WindowAttributesConfig> for WindowAttributes {
         }
     }
 }
+
+/// Changes that can be applied to a live window through `WindowManager::update_window`.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WindowUpdate {
+    pub label: Option<String>,
+    pub inner_size: Option<Size>,
+    pub resizable: Option<bool>,
+    pub minimizable: Option<bool>,
+    pub maximizable: Option<bool>,
+    pub closable: Option<bool>,
+    pub title: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub maximized: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub visible: Option<bool>,
+    pub transparent: Option<bool>,
+    pub decorations: Option<bool>,
+    pub always_on_top: Option<bool>,
+    pub always_on_bottom: Option<bool>,
+    pub focused: Option<bool>,
+    pub content_protection: Option<bool>,
+    pub visible_on_all_workspaces: Option<bool>,
+    pub background_color: Option<(u8, u8, u8, u8)>,
+    pub webview: Option<Value>,
+}
+
+impl WindowUpdate {
+    /// Rejects unknown keys and attributes that only take effect at creation: `label`, `transparent`,
+    /// `webview`, and `focused: false`, which has no platform equivalent.
+    pub fn from_value(value: Value) -> Result<Self, String> {
+        let update: WindowUpdate = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        if update.label.is_some() {
+            return Err("`label` cannot be changed after the window is created".to_string());
+        }
+        if update.transparent.is_some() {
+            return Err("`transparent` cannot be changed after the window is created".to_string());
+        }
+        if update.webview.is_some() {
+            return Err("`webview` cannot be updated here, use update_webview instead".to_string());
+        }
+        if update.focused == Some(false) {
+            return Err("`focused` can only be set to true, focus another window instead".to_string());
+        }
+        Ok(update)
+    }
+
+    pub fn apply(&self, window: &Window) {
+        if let Some(size) = self.inner_size {
+            window.set_inner_size(size);
+        }
+        if self.width.is_some() || self.height.is_some() {
+            let current = window.inner_size().to_logical::<u32>(window.scale_factor());
+            window.set_inner_size(LogicalSize {
+                width: self.width.unwrap_or(current.width),
+                height: self.height.unwrap_or(current.height),
+            });
+        }
+        if let Some(resizable) = self.resizable {
+            window.set_resizable(resizable);
+        }
+        if let Some(minimizable) = self.minimizable {
+            window.set_minimizable(minimizable);
+        }
+        if let Some(maximizable) = self.maximizable {
+            window.set_maximizable(maximizable);
+        }
+        if let Some(closable) = self.closable {
+            window.set_closable(closable);
+        }
+        if let Some(title) = &self.title {
+            window.set_title(title);
+        }
+        if let Some(maximized) = self.maximized {
+            window.set_maximized(maximized);
+        }
+        if let Some(fullscreen) = self.fullscreen {
+            window.set_fullscreen(if fullscreen {
+                Some(Fullscreen::Borderless(None))
+            } else {
+                None
+            });
+        }
+        if let Some(visible) = self.visible {
+            window.set_visible(visible);
+        }
+        if let Some(decorations) = self.decorations {
+            window.set_decorations(decorations);
+        }
+        if let Some(always_on_top) = self.always_on_top {
+            window.set_always_on_top(always_on_top);
+        }
+        if let Some(always_on_bottom) = self.always_on_bottom {
+            window.set_always_on_bottom(always_on_bottom);
+        }
+        if self.focused == Some(true) {
+            window.set_focus();
+        }
+        if let Some(content_protection) = self.content_protection {
+            window.set_content_protection(content_protection);
+        }
+        if let Some(visible_on_all_workspaces) = self.visible_on_all_workspaces {
+            window.set_visible_on_all_workspaces(visible_on_all_workspaces);
+        }
+        if let Some(background_color) = self.background_color {
+            window.set_background_color(Some(background_color));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_window_updates() {
+        let update = WindowUpdate::from_value(json!({"title": "Docs", "width": 640, "focused": true})).unwrap();
+        assert_eq!(update.title.as_deref(), Some("Docs"));
+        assert_eq!(update.width, Some(640));
+        assert_eq!(update.focused, Some(true));
+        assert!(WindowUpdate::from_value(json!({})).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_wrong_types() {
+        let unknown = WindowUpdate::from_value(json!({"titel": "Docs"})).unwrap_err();
+        assert!(unknown.contains("unknown field `titel`"), "{}", unknown);
+        assert!(WindowUpdate::from_value(json!({"width": "640"})).is_err());
+        assert!(WindowUpdate::from_value(json!({"width": -1})).is_err());
+        assert!(WindowUpdate::from_value(json!({"resizable": 1})).is_err());
+        assert!(WindowUpdate::from_value(json!(["title"])).is_err());
+    }
+
+    #[test]
+    fn rejects_attributes_fixed_at_creation() {
+        for updates in [
+            json!({"transparent": true}),
+            json!({"webview": {"url": "https://example.com"}}),
+            json!({"label": "other"}),
+            json!({"focused": false}),
+        ] {
+            assert!(WindowUpdate::from_value(updates.clone()).is_err(), "{} was accepted", updates);
+        }
+    }
+}